@@ -0,0 +1,145 @@
+use ncurses::{
+    KEY_BACKSPACE, KEY_DC, KEY_DOWN, KEY_END, KEY_ENTER, KEY_HOME, KEY_LEFT, KEY_RIGHT, KEY_UP,
+};
+
+/// An input to a player's field. Humans produce these from keys and the
+/// CPU produces the same ones, so both play by the same rules.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Left,
+    Right,
+    Down,
+    Rotate,
+}
+
+impl Action {
+    pub fn all() -> [Action; 4] {
+        [Action::Left, Action::Right, Action::Down, Action::Rotate]
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Down => "down",
+            Action::Rotate => "rotate",
+        }
+    }
+}
+
+/// The key bound to each action of one player.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keys {
+    pub left: i32,
+    pub right: i32,
+    pub down: i32,
+    pub rotate: i32,
+}
+
+/// Keys with a name of their own; other printable keys are named by
+/// their character.
+const KEY_NAMES: [(&str, i32); 11] = [
+    ("left", KEY_LEFT),
+    ("right", KEY_RIGHT),
+    ("up", KEY_UP),
+    ("down", KEY_DOWN),
+    ("space", 0x20),
+    ("enter", 0x0a),
+    ("tab", 0x09),
+    ("backspace", KEY_BACKSPACE),
+    ("delete", KEY_DC),
+    ("home", KEY_HOME),
+    ("end", KEY_END),
+];
+
+/// Quits the game whatever the bindings.
+pub const QUIT: i32 = 0x71;
+
+impl Keys {
+    /// Arrows, and space to rotate.
+    pub fn first() -> Self {
+        Keys {
+            left: KEY_LEFT,
+            right: KEY_RIGHT,
+            down: KEY_DOWN,
+            rotate: 0x20,
+        }
+    }
+
+    /// Keys of a second human on the same keyboard.
+    pub fn second() -> Self {
+        Keys {
+            left: 0x61,
+            right: 0x64,
+            down: 0x73,
+            rotate: 0x77,
+        }
+    }
+
+    pub fn action(&self, key: i32) -> Option<Action> {
+        Action::all().iter().cloned().find(|&a| self.key(a) == key)
+    }
+
+    pub fn key(&self, action: Action) -> i32 {
+        match action {
+            Action::Left => self.left,
+            Action::Right => self.right,
+            Action::Down => self.down,
+            Action::Rotate => self.rotate,
+        }
+    }
+
+    pub fn bind(&mut self, action: Action, key: i32) {
+        match action {
+            Action::Left => self.left = key,
+            Action::Right => self.right = key,
+            Action::Down => self.down = key,
+            Action::Rotate => self.rotate = key,
+        }
+    }
+}
+
+/// How a key is written in the settings file and shown in menus.
+pub fn key_name(key: i32) -> Option<String> {
+    if let Some(&(name, _)) = KEY_NAMES.iter().find(|&&(_, k)| k == key) {
+        return Some(name.to_string());
+    }
+    match key {
+        KEY_ENTER | 0x0d => Some("enter".to_string()),
+        0x21..=0x7e => Some((key as u8 as char).to_string()),
+        _ => None,
+    }
+}
+
+/// A key from its name, refusing the one that quits.
+pub fn parse_key(name: &str) -> Result<i32, String> {
+    if let Some(&(_, key)) = KEY_NAMES.iter().find(|&&(n, _)| n == name) {
+        return Ok(key);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c as i32 == QUIT => Err("q is kept for quitting".to_string()),
+        (Some(c), None) if c.is_ascii_graphic() => Ok(c as i32),
+        _ => Err(format!("unknown key: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{key_name, parse_key, Action, Keys};
+    use ncurses::KEY_LEFT;
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(Keys::first().action(KEY_LEFT), Some(Action::Left));
+        assert_eq!(Keys::second().action(0x77), Some(Action::Rotate));
+        assert_eq!(Keys::first().action(0x77), None);
+        for &key in &[KEY_LEFT, 0x20, 0x6a] {
+            assert_eq!(parse_key(&key_name(key).unwrap()), Ok(key));
+        }
+        assert_eq!(parse_key("space"), Ok(0x20));
+        assert!(parse_key("q").is_err());
+        assert!(parse_key("f13").is_err());
+    }
+}
@@ -0,0 +1,477 @@
+use std::cmp::Reverse;
+use std::str::FromStr;
+
+use action::Action;
+use bitboard::BitBoard;
+use chain::Chain;
+use direction::Direction;
+use field::Field;
+use piece::Piece;
+use position::Position;
+use potential::Potential;
+use poyo::Poyo;
+use poyopoyo::PoyoPoyo;
+
+/// Offsets of the second poyo from the pivot, in the order `rotate` visits them.
+const ROTATIONS: [(i32, i32); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+/// Placements whose potential chain is searched in `Level::Hard`.
+const BEAM: usize = 4;
+/// Pairs of the preview queue `Level::Hard` looks ahead over.
+const LOOK_AHEAD: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    /// Pops whatever it can as soon as it can.
+    Easy,
+    /// Picks the best placement for the current pair only.
+    Normal,
+    /// Builds chains, looking ahead over the preview queue.
+    Hard,
+}
+
+impl FromStr for Level {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "easy" => Ok(Level::Easy),
+            "normal" => Ok(Level::Normal),
+            "hard" => Ok(Level::Hard),
+            _ => Err(format!("unknown level: {}", s)),
+        }
+    }
+}
+
+impl Level {
+    /// Frames the CPU waits between two actions.
+    fn interval(&self) -> u32 {
+        match *self {
+            Level::Easy => 30,
+            Level::Normal => 12,
+            Level::Hard => 4,
+        }
+    }
+}
+
+/// Where the current pair should end up: the pivot's column and how many
+/// times it is rotated from its spawn orientation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    pub x: i32,
+    pub rotation: usize,
+}
+
+impl Placement {
+    pub fn new(x: i32, rotation: usize) -> Self {
+        Placement { x, rotation }
+    }
+
+    /// Every placement that keeps both poyos inside a field `width` wide.
+    pub fn all(width: usize) -> Vec<Placement> {
+        let mut res = vec![];
+        for (rotation, &(dx, _)) in ROTATIONS.iter().enumerate() {
+            for x in 0..width as i32 {
+                let child = x + dx;
+                if 0 <= child && child < width as i32 {
+                    res.push(Placement::new(x, rotation));
+                }
+            }
+        }
+        res
+    }
+
+    /// Rotation count of `pair` from its spawn orientation.
+    pub fn rotation_of(pair: &PoyoPoyo) -> usize {
+        let offset = (pair.1.x() - pair.0.x(), pair.1.y() - pair.0.y());
+        ROTATIONS.iter().position(|&o| o == offset).unwrap_or(0)
+    }
+
+    /// `pair` moved to this placement, hovering over the field.
+    pub fn apply(&self, pair: &PoyoPoyo) -> PoyoPoyo {
+        let (dx, dy) = ROTATIONS[self.rotation];
+        PoyoPoyo::new(
+            Poyo::new(Position::new(self.x, 1), pair.0.color()),
+            Poyo::new(Position::new(self.x + dx, 1 + dy), pair.1.color()),
+        )
+    }
+
+    /// Next action that brings the current pair closer to this placement.
+    /// When the way is blocked the pair is pushed down, which eventually
+    /// fixes it. Larger pieces are only moved, `x` being the left of their
+    /// box.
+    pub fn step(&self, field: &Field) -> Action {
+        let current = match field.current() {
+            Some(c) => c,
+            None => return Action::Down,
+        };
+        let (x, rotation) = match current.pair() {
+            Some(pair) => (pair.0.x(), Placement::rotation_of(pair)),
+            None => (current.left(), self.rotation),
+        };
+        if rotation != self.rotation {
+            if field.current_can_rotate() {
+                return Action::Rotate;
+            }
+        } else if x < self.x {
+            if field.current_can_move(&Direction::Right) {
+                return Action::Right;
+            }
+        } else if x > self.x && field.current_can_move(&Direction::Left) {
+            return Action::Left;
+        }
+        Action::Down
+    }
+}
+
+/// A CPU player. It decides on a placement once per pair and then walks
+/// the pair there one action at a time.
+pub struct Ai {
+    level: Level,
+    target: Option<Placement>,
+    wait: u32,
+}
+
+impl Ai {
+    pub fn new(level: Level) -> Self {
+        Ai {
+            level,
+            target: None,
+            wait: 0,
+        }
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    /// Forgets the plan; called whenever a new pair appears.
+    pub fn reset(&mut self) {
+        self.target = None;
+    }
+
+    pub fn action(&mut self, field: &Field) -> Option<Action> {
+        if self.wait > 0 {
+            self.wait -= 1;
+            return None;
+        }
+        self.wait = self.level.interval();
+        field.current()?;
+        let target = match self.target {
+            Some(target) => target,
+            None => {
+                let target = self.think(field);
+                self.target = Some(target);
+                target
+            }
+        };
+        Some(target.step(field))
+    }
+
+    /// Chooses a placement for the current pair. Fields too big for a
+    /// `BitBoard` are searched on a copy of the `Field`, which is slower,
+    /// so only one pair ahead.
+    pub fn think(&self, field: &Field) -> Placement {
+        let current = match field.current().map(|c| (c, c.pair())) {
+            Some((_, Some(pair))) => pair.clone(),
+            Some((piece, None)) => return self.think_boxed(field, piece),
+            None => return Placement::new(1, 0),
+        };
+        let queue: Vec<&PoyoPoyo> = field
+            .next()
+            .iter()
+            .map_while(|next| next.pair())
+            .take(LOOK_AHEAD)
+            .collect();
+        match BitBoard::from_field(field) {
+            Some(board) => self.search(&board, &current, &queue),
+            None => self.search(&field.simulate(), &current, &queue[..queue.len().min(1)]),
+        }
+    }
+
+    /// Best placement of `current` on `board`, `queue` coming after it.
+    fn search<B: Board>(&self, board: &B, current: &PoyoPoyo, queue: &[&PoyoPoyo]) -> Placement {
+        let mut candidates: Vec<(i64, Placement, B)> = vec![];
+        for placement in Placement::all(board.width()) {
+            let mut sim = board.clone();
+            if let Some(chain) = sim.drop_pair(&placement.apply(current)) {
+                let value = match self.level {
+                    Level::Easy => greedy(&chain, &sim),
+                    Level::Normal => fire(&chain, &sim, 1) + shape(&sim),
+                    Level::Hard => fire(&chain, &sim, 3) + look_ahead(&sim, queue),
+                };
+                candidates.push((value, placement, sim));
+            }
+        }
+        candidates.sort_by_key(|c| Reverse(c.0));
+        if self.level == Level::Hard {
+            for candidate in candidates.iter_mut().take(BEAM) {
+                let chain = candidate.2.potential().map(|p| p.chain).unwrap_or(0);
+                candidate.0 += chain as i64 * 500;
+            }
+            candidates.sort_by_key(|c| Reverse(c.0));
+        }
+        candidates
+            .first()
+            .map(|c| c.1)
+            .unwrap_or_else(|| Placement::new(1, 0))
+    }
+
+    /// Chooses a column for a piece larger than a pair, dropped as dealt.
+    fn think_boxed(&self, field: &Field, piece: &Piece) -> Placement {
+        let width = piece.right() - piece.left() + 1;
+        let mut best: Option<(i64, Placement)> = None;
+        for x in 0..=field.width() as i32 - width {
+            let mut moved = piece.clone();
+            while moved.left() < x {
+                moved.moves(Direction::Right);
+            }
+            while moved.left() > x {
+                moved.moves(Direction::Left);
+            }
+            let mut sim = field.simulate();
+            let chain = match sim.drop_piece(&moved) {
+                Some(chain) => chain,
+                None => continue,
+            };
+            let value = match self.level {
+                Level::Easy => greedy(&chain, &sim),
+                _ => fire(&chain, &sim, 1) + shape(&sim),
+            };
+            if best.map_or(true, |(v, _)| value > v) {
+                best = Some((value, Placement::new(x, 0)));
+            }
+        }
+        best.map(|b| b.1).unwrap_or_else(|| Placement::new(1, 0))
+    }
+}
+
+/// What the search needs of a board, met by `BitBoard` where the field
+/// fits and by a `Field` copy where it does not.
+trait Board: Clone {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn column_height(&self, x: i32) -> usize;
+    fn drop_pair(&mut self, pair: &PoyoPoyo) -> Option<Chain>;
+    /// Pairs of same-colored neighbours, garbage left out.
+    fn connections(&self) -> u32;
+    fn potential(&self) -> Option<Potential>;
+}
+
+impl Board for BitBoard {
+    fn width(&self) -> usize {
+        BitBoard::width(self)
+    }
+
+    fn height(&self) -> usize {
+        BitBoard::height(self)
+    }
+
+    fn column_height(&self, x: i32) -> usize {
+        BitBoard::column_height(self, x)
+    }
+
+    fn drop_pair(&mut self, pair: &PoyoPoyo) -> Option<Chain> {
+        BitBoard::drop_pair(self, pair)
+    }
+
+    fn connections(&self) -> u32 {
+        BitBoard::connections(self)
+    }
+
+    fn potential(&self) -> Option<Potential> {
+        BitBoard::potential(self)
+    }
+}
+
+impl Board for Field {
+    fn width(&self) -> usize {
+        Field::width(self)
+    }
+
+    fn height(&self) -> usize {
+        Field::height(self)
+    }
+
+    fn column_height(&self, x: i32) -> usize {
+        Field::column_height(self, x)
+    }
+
+    fn drop_pair(&mut self, pair: &PoyoPoyo) -> Option<Chain> {
+        Field::drop_pair(self, pair)
+    }
+
+    fn connections(&self) -> u32 {
+        let rows = self.rows();
+        let same = |a: &Option<Poyo>, b: &Option<Poyo>| match (*a, *b) {
+            (Some(a), Some(b)) => a.color().pops() && a.is_same_color(&b),
+            _ => false,
+        };
+        let mut count = 0;
+        for (y, row) in rows.iter().enumerate() {
+            for x in 0..row.len() {
+                if x + 1 < row.len() && same(&row[x], &row[x + 1]) {
+                    count += 1;
+                }
+                if y + 1 < rows.len() && same(&row[x], &rows[y + 1][x]) {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn potential(&self) -> Option<Potential> {
+        Field::potential(self)
+    }
+}
+
+/// Whether the spawn cells are in danger of being covered.
+fn is_dying<B: Board>(board: &B) -> bool {
+    board.column_height(1) + 3 >= board.height()
+}
+
+/// Value of an easy placement: pop as much as possible, else stay low.
+fn greedy<B: Board>(chain: &Chain, board: &B) -> i64 {
+    chain.removed() as i64 * 100 - max_height(board) as i64
+}
+
+/// Value of firing `chain`. Chains shorter than `wanted` waste poyos that
+/// could have built something bigger, unless the field is about to top out.
+fn fire<B: Board>(chain: &Chain, board: &B, wanted: usize) -> i64 {
+    if is_dying(board) {
+        return -100_000;
+    }
+    if chain.is_empty() {
+        0
+    } else if chain.len() >= wanted || column_danger(board) {
+        chain.score() as i64 * 4
+    } else {
+        -(chain.removed() as i64) * 30
+    }
+}
+
+/// Best value over every way of placing the pairs of `queue` in order on
+/// `board`, or the shape of `board` once the queue is used up.
+fn look_ahead<B: Board>(board: &B, queue: &[&PoyoPoyo]) -> i64 {
+    let (next, rest) = match queue.split_first() {
+        Some(split) => split,
+        None => return shape(board),
+    };
+    Placement::all(board.width())
+        .into_iter()
+        .filter_map(|placement| {
+            let mut sim = board.clone();
+            sim.drop_pair(&placement.apply(next))
+                .map(|chain| fire(&chain, &sim, 3) + look_ahead(&sim, rest))
+        })
+        .max()
+        .unwrap_or(-100_000)
+}
+
+/// Rewards same-colored neighbours and penalises tall or uneven stacks.
+fn shape<B: Board>(board: &B) -> i64 {
+    let mut value = board.connections() as i64 * 20;
+    let heights: Vec<i64> = (0..board.width() as i32)
+        .map(|x| board.column_height(x) as i64)
+        .collect();
+    for pair in heights.windows(2) {
+        value -= (pair[0] - pair[1]).abs() * 5;
+    }
+    value - heights[1] * heights[1] * 2
+}
+
+fn max_height<B: Board>(board: &B) -> usize {
+    (0..board.width() as i32)
+        .map(|x| board.column_height(x))
+        .max()
+        .unwrap_or(0)
+}
+
+fn column_danger<B: Board>(board: &B) -> bool {
+    max_height(board) + 4 >= board.height()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Ai, Level, Placement};
+    use action::Action;
+    use color::Color;
+    use direction::Direction;
+    use field::Field;
+    use piece::Piece;
+    use position::Position;
+    use poyo::Poyo;
+    use poyopoyo::PoyoPoyo;
+    use size::Size;
+
+    fn pair(x: i32, c1: Color, c2: Color) -> PoyoPoyo {
+        PoyoPoyo::new(
+            Poyo::new(Position::new(x, 0), c1),
+            Poyo::new(Position::new(x, 1), c2),
+        )
+    }
+
+    #[test]
+    fn test_placements() {
+        assert_eq!(Placement::all(6).len(), 22);
+        let spawn = PoyoPoyo::rand();
+        assert_eq!(Placement::rotation_of(&spawn), 0);
+        let mut rotated = spawn.clone();
+        for i in 1..4 {
+            rotated.rotate();
+            assert_eq!(Placement::rotation_of(&rotated), i);
+        }
+    }
+
+    fn stack(field: &mut Field, x: i32, colors: &[Color]) {
+        for &color in colors {
+            field.drop_poyo(Poyo::new(Position::new(x, 0), color));
+        }
+    }
+
+    #[test]
+    fn test_easy_pops_when_possible() {
+        let mut field = Field::default();
+        stack(&mut field, 4, &[Color::Red, Color::Red]);
+        field.set_current(pair(1, Color::Red, Color::Red));
+        let target = Ai::new(Level::Easy).think(&field);
+        let mut sim = field.simulate();
+        let chain = sim
+            .drop_pair(&target.apply(field.current().unwrap().pair().unwrap()))
+            .unwrap();
+        assert_eq!(chain.removed(), 4);
+    }
+
+    #[test]
+    fn test_searches_fields_too_big_for_a_bitboard() {
+        let mut field = Field::new(Size::new(10, 20));
+        stack(&mut field, 8, &[Color::Red, Color::Red]);
+        field.set_current(pair(1, Color::Red, Color::Red));
+        for &level in &[Level::Easy, Level::Normal, Level::Hard] {
+            let target = Ai::new(level).think(&field);
+            assert!(Placement::all(10).contains(&target));
+        }
+        let target = Ai::new(Level::Easy).think(&field);
+        let mut sim = field.simulate();
+        let chain = sim
+            .drop_pair(&target.apply(field.current().unwrap().pair().unwrap()))
+            .unwrap();
+        assert_eq!(chain.removed(), 4);
+    }
+
+    #[test]
+    fn test_places_larger_pieces() {
+        let mut field = Field::default();
+        stack(&mut field, 5, &[Color::Red, Color::Red, Color::Red]);
+        field.set_current(Piece::big(Color::Red));
+        let target = Ai::new(Level::Easy).think(&field);
+        assert!(target.x >= 3);
+        while field.current().is_some_and(|c| c.left() != target.x) {
+            assert_eq!(target.step(&field), Action::Right);
+            field.move_current(Direction::Right);
+        }
+        assert_eq!(target.step(&field), Action::Down);
+        let mut sim = field.simulate();
+        let chain = sim.drop_piece(field.current().unwrap()).unwrap();
+        assert_eq!(chain.removed(), 7);
+    }
+}
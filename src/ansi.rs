@@ -0,0 +1,105 @@
+//! Draws a field as plain text with ANSI escapes, for output that is not
+//! an ncurses screen. Colors are always written as RGB.
+
+use color::Color;
+use field::Field;
+use theme::{Attr, Rgb, Swatch, Theme};
+
+const RESET: &str = "\x1b[0m";
+
+/// `text` styled as `swatch` says.
+pub fn paint(swatch: &Swatch, text: &str) -> String {
+    let mut codes: Vec<String> = swatch
+        .attrs
+        .iter()
+        .map(|&attr| {
+            match attr {
+                Attr::Bold => "1",
+                Attr::Dim => "2",
+                Attr::Underline => "4",
+                Attr::Reverse => "7",
+            }
+            .to_string()
+        })
+        .collect();
+    if let Some(Rgb(r, g, b)) = swatch.fg {
+        codes.push(format!("38;2;{};{};{}", r, g, b));
+    }
+    if let Some(Rgb(r, g, b)) = swatch.bg {
+        codes.push(format!("48;2;{};{};{}", r, g, b));
+    }
+    if codes.is_empty() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{}{}", codes.join(";"), text, RESET)
+}
+
+/// The well of `field` with two-column cells, the preview to its right and
+/// the `garbage` waiting to fall below it.
+pub fn render(field: &Field, theme: &Theme, garbage: usize) -> Vec<String> {
+    let cell = |color: Color| paint(theme.swatch(color), &theme.swatch(color).text(2));
+    let border = &theme.well;
+    let horizontal = border.horizontal.repeat(field.width() * 2);
+    let mut lines = vec![format!(
+        "{}{}{}  next",
+        border.top_left, horizontal, border.top_right
+    )];
+    for (y, row) in field.rows().iter().enumerate() {
+        let mut line = border.vertical.clone();
+        for poyo in row {
+            match *poyo {
+                Some(p) => line += &cell(p.color()),
+                None => line += "  ",
+            }
+        }
+        line += &border.vertical;
+        if y < 2 {
+            line += " ";
+            for piece in field.next() {
+                line += " ";
+                for c in piece.grid().get(y).into_iter().flatten() {
+                    match *c {
+                        Some(color) => line += &cell(color),
+                        None => line += "  ",
+                    }
+                }
+            }
+        }
+        lines.push(line);
+    }
+    lines.push(format!(
+        "{}{}{}",
+        border.bottom_left, horizontal, border.bottom_right
+    ));
+    if garbage > 0 {
+        lines.push(format!("{} {}", cell(Color::Garbage), garbage));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::render;
+    use field::Field;
+    use theme::Theme;
+
+    #[test]
+    fn test_render_colorblind() {
+        let field = Field::from_lines(&[
+            "......", "......", "......", "......", "......", "......", "......", "......",
+            "......", "......", "......", "RGYB..",
+        ])
+        .unwrap();
+        let theme = Theme::default().with_colorblind();
+        let lines = render(&field, &theme, 3);
+        assert_eq!(lines.len(), 12 + 3);
+        let bottom = &lines[12];
+        let shapes: Vec<usize> = ["●", "▲", "■", "◆"]
+            .iter()
+            .map(|s| bottom.find(s).unwrap())
+            .collect();
+        assert!(shapes.windows(2).all(|w| w[0] < w[1]));
+        assert!(bottom.starts_with('|') && bottom.contains("\x1b["));
+        assert!(lines[14].contains("★") && lines[14].ends_with(" 3"));
+    }
+}
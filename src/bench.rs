@@ -0,0 +1,123 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use ai::{Ai, Level, Placement};
+use bitboard::BitBoard;
+use color::Color;
+use field::Field;
+use position::Position;
+use poyo::Poyo;
+use poyopoyo::PoyoPoyo;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use size::Size;
+
+/// Boards every measurement is averaged over.
+const BOARDS: usize = 32;
+
+/// Settled boards with random stacks, the same ones for every seed.
+fn boards(seed: u32) -> Vec<Field> {
+    let mut rng = XorShiftRng::from_seed([seed, 0x6c07_8965, 0x2545_f491, 0x9e37_79b9]);
+    (0..BOARDS)
+        .map(|_| {
+            let mut field = Field::with_seed(Size::new(6, 12), rng.gen());
+            for _ in 0..rng.gen_range(10, 40) {
+                let x = rng.gen_range(0, 6);
+                field.drop_poyo(Poyo::new(Position::new(x, 0), Color::from(rng.gen::<u8>())));
+            }
+            field.resolve();
+            field
+        })
+        .collect()
+}
+
+/// Average time of one call of `f` in microseconds.
+fn measure<F: FnMut()>(iterations: usize, mut f: F) -> f64 {
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed();
+    (elapsed.as_secs() as f64 * 1e6 + f64::from(elapsed.subsec_nanos()) / 1e3) / iterations as f64
+}
+
+fn report(name: &str, field: f64, bitboard: f64) {
+    println!(
+        "{:<28} field {:>10.2} us  bitboard {:>8.2} us  {:>6.1}x",
+        name,
+        field,
+        bitboard,
+        field / bitboard
+    );
+}
+
+/// Times `Field` against `BitBoard` on the operations the AI repeats most,
+/// over `iterations` rounds of the same boards.
+pub fn run(iterations: usize, seed: u32) {
+    let fields = boards(seed);
+    let bitboards: Vec<BitBoard> = fields
+        .iter()
+        .map(|f| BitBoard::from_field(f).expect("6x12 fits in a bitboard"))
+        .collect();
+    let pair = PoyoPoyo::rand_from(&mut XorShiftRng::from_seed([seed, 1, 2, 3]), 4);
+    let placements = Placement::all(6);
+
+    let field = measure(iterations, || {
+        for f in &fields {
+            black_box(f.clone().resolve());
+        }
+    });
+    let bitboard = measure(iterations, || {
+        for b in &bitboards {
+            black_box(b.clone().resolve());
+        }
+    });
+    report("resolve", field / BOARDS as f64, bitboard / BOARDS as f64);
+
+    let field = measure(iterations, || {
+        for f in &fields {
+            for p in &placements {
+                black_box(f.clone().drop_pair(&p.apply(&pair)));
+            }
+        }
+    });
+    let bitboard = measure(iterations, || {
+        for b in &bitboards {
+            for p in &placements {
+                black_box(b.clone().drop_pair(&p.apply(&pair)));
+            }
+        }
+    });
+    report(
+        "drop pair, all placements",
+        field / BOARDS as f64,
+        bitboard / BOARDS as f64,
+    );
+
+    let field = measure(iterations, || {
+        for f in &fields {
+            black_box(f.potential());
+        }
+    });
+    let bitboard = measure(iterations, || {
+        for b in &bitboards {
+            black_box(b.potential());
+        }
+    });
+    report("potential", field / BOARDS as f64, bitboard / BOARDS as f64);
+
+    let ai = Ai::new(Level::Hard);
+    let mut playing: Vec<Field> = fields.clone();
+    for f in playing.iter_mut() {
+        f.spawn();
+    }
+    let think = measure(iterations, || {
+        for f in &playing {
+            black_box(ai.think(f));
+        }
+    });
+    println!(
+        "{:<28} {:>16.2} us per pair",
+        "hard cpu think",
+        think / BOARDS as f64
+    );
+}
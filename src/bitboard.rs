@@ -0,0 +1,356 @@
+use chain::{Chain, ChainStep};
+use color::Color;
+use field::Field;
+use position::Position;
+use potential::Potential;
+use poyopoyo::PoyoPoyo;
+
+/// Bits reserved for each column.
+const STRIDE: usize = 16;
+pub const MAX_WIDTH: usize = 128 / STRIDE;
+pub const MAX_HEIGHT: usize = STRIDE;
+/// Every color a cell can hold, in the order of `BitBoard::colors`.
+const KINDS: [Color; 5] = [
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Garbage,
+];
+
+fn kind(color: Color) -> usize {
+    color as usize - 1
+}
+
+/// A settled field packed into one bitmask per color, for fast look-ahead.
+/// Bit `x * 16 + h` is the cell in column `x` at `h` rows above the
+/// bottom. Groups, gravity and pops work on whole masks at once and give
+/// the same results as `Field`. Fields up to 8 wide and 16 tall fit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BitBoard {
+    width: usize,
+    height: usize,
+    colors: [u128; 5],
+    /// Bits of cells inside the field.
+    valid: u128,
+    /// The lowest bit of every column.
+    bottom: u128,
+    /// The highest bit of every column stride.
+    top: u128,
+}
+
+impl BitBoard {
+    pub fn new(width: usize, height: usize) -> Option<BitBoard> {
+        if width == 0 || height == 0 || width > MAX_WIDTH || height > MAX_HEIGHT {
+            return None;
+        }
+        let column = if height == STRIDE {
+            u128::from(u16::MAX)
+        } else {
+            (1u128 << height) - 1
+        };
+        let mut board = BitBoard {
+            width,
+            height,
+            colors: [0; 5],
+            valid: 0,
+            bottom: 0,
+            top: 0,
+        };
+        for x in 0..MAX_WIDTH {
+            if x < width {
+                board.valid |= column << (x * STRIDE);
+            }
+            board.bottom |= 1 << (x * STRIDE);
+            board.top |= 1 << (x * STRIDE + STRIDE - 1);
+        }
+        Some(board)
+    }
+
+    /// The settled part of `field`, leaving out the current pair, with
+    /// special poyos taken for plain garbage. `None` if the field is too big.
+    pub fn from_field(field: &Field) -> Option<BitBoard> {
+        let mut board = BitBoard::new(field.width(), field.height())?;
+        for p in field.simulate().rows().iter().flatten().flatten() {
+            let color = if p.color().pops() {
+                p.color()
+            } else {
+                Color::Garbage
+            };
+            board.set(p.x(), p.y(), Some(color));
+        }
+        Some(board)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Bit of the cell at `(x, y)`, counting `y` from the top like `Field`.
+    fn bit(&self, x: i32, y: i32) -> u128 {
+        1 << (x as usize * STRIDE + (self.height - 1 - y as usize))
+    }
+
+    pub fn get(&self, x: i32, y: i32) -> Option<Color> {
+        let bit = self.bit(x, y);
+        KINDS
+            .iter()
+            .zip(self.colors.iter())
+            .find(|&(_, mask)| mask & bit != 0)
+            .map(|(&color, _)| color)
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, color: Option<Color>) {
+        let bit = self.bit(x, y);
+        for mask in self.colors.iter_mut() {
+            *mask &= !bit;
+        }
+        if let Some(color) = color {
+            self.colors[kind(color)] |= bit;
+        }
+    }
+
+    pub fn occupied(&self) -> u128 {
+        self.colors.iter().fold(0, |acc, mask| acc | mask)
+    }
+
+    fn column(&self, mask: u128, x: i32) -> u16 {
+        (mask >> (x as usize * STRIDE)) as u16
+    }
+
+    pub fn column_height(&self, x: i32) -> usize {
+        self.column(self.occupied(), x).count_ones() as usize
+    }
+
+    /// Puts a poyo on top of column `x`. Returns false if it is full.
+    pub fn drop_poyo(&mut self, x: i32, color: Color) -> bool {
+        if x < 0 || self.width as i32 <= x {
+            return false;
+        }
+        let h = self.column_height(x);
+        if h >= self.height {
+            return false;
+        }
+        self.colors[kind(color)] |= 1 << (x as usize * STRIDE + h);
+        true
+    }
+
+    /// Same as `Field::drop_pair`.
+    pub fn drop_pair(&mut self, pair: &PoyoPoyo) -> Option<Chain> {
+        let (lower, upper) = if pair.0.y() >= pair.1.y() {
+            (pair.0, pair.1)
+        } else {
+            (pair.1, pair.0)
+        };
+        if self.drop_poyo(lower.x(), lower.color()) && self.drop_poyo(upper.x(), upper.color()) {
+            Some(self.resolve())
+        } else {
+            None
+        }
+    }
+
+    /// `mask` grown by one cell in every direction.
+    fn expand(&self, mask: u128) -> u128 {
+        let up = (mask << 1) & !self.bottom;
+        let down = (mask >> 1) & !self.top;
+        (mask | up | down | (mask << STRIDE) | (mask >> STRIDE)) & self.valid
+    }
+
+    /// The group of cells in `mask` connected to `seed`.
+    fn flood(&self, seed: u128, mask: u128) -> u128 {
+        let mut group = seed;
+        loop {
+            let grown = self.expand(group) & mask;
+            if grown == group {
+                return group;
+            }
+            group = grown;
+        }
+    }
+
+    /// Lets every poyo fall, one row per round for all columns at once.
+    pub fn fall(&mut self) {
+        loop {
+            let occupied = self.occupied();
+            let empty = self.valid & !occupied;
+            let falling = occupied & (empty << 1) & !self.bottom;
+            if falling == 0 {
+                break;
+            }
+            for mask in self.colors.iter_mut() {
+                *mask = (*mask & !falling) | ((*mask & falling) >> 1);
+            }
+        }
+    }
+
+    /// Pops every group of four or more, and the garbage next to them.
+    pub fn check(&mut self) -> ChainStep {
+        let mut step = ChainStep::default();
+        let mut popped = 0;
+        for (i, &color) in KINDS.iter().enumerate().take(4) {
+            let mut rest = self.colors[i];
+            while rest != 0 {
+                let group = self.flood(rest & rest.wrapping_neg(), self.colors[i]);
+                rest &= !group;
+                if group.count_ones() >= 4 {
+                    step.push(color, group.count_ones() as usize);
+                    popped |= group;
+                }
+            }
+        }
+        if popped != 0 {
+            let garbage = kind(Color::Garbage);
+            self.colors[garbage] &= !self.expand(popped);
+            for mask in self.colors.iter_mut() {
+                *mask &= !popped;
+            }
+        }
+        step
+    }
+
+    /// Same as `Field::resolve`.
+    pub fn resolve(&mut self) -> Chain {
+        let mut chain = Chain::default();
+        self.fall();
+        loop {
+            let step = self.check();
+            if step.is_empty() {
+                break;
+            }
+            chain.push(step);
+            self.fall();
+        }
+        chain
+    }
+
+    /// Pairs of same-colored neighbours, garbage left out.
+    pub fn connections(&self) -> u32 {
+        self.colors[..4]
+            .iter()
+            .map(|&mask| {
+                let vertical = mask & ((mask << 1) & !self.bottom);
+                let horizontal = mask & (mask << STRIDE);
+                vertical.count_ones() + horizontal.count_ones()
+            })
+            .sum()
+    }
+
+    /// Same as `Field::potential`.
+    pub fn potential(&self) -> Option<Potential> {
+        let mut best: Option<Potential> = None;
+        for x in 0..self.width as i32 {
+            let h = self.column_height(x) as i32;
+            let trigger = Position::new(x, self.height as i32 - 1 - h);
+            for &color in Color::all().iter() {
+                let mut sim = *self;
+                for count in 1..3 {
+                    if !sim.drop_poyo(x, color) {
+                        break;
+                    }
+                    let mut fired = sim;
+                    let chain = fired.resolve();
+                    if chain.is_empty() {
+                        continue;
+                    }
+                    let found = Potential {
+                        chain: chain.len(),
+                        score: chain.score(),
+                        trigger,
+                        color,
+                        count,
+                    };
+                    if best.map(|b| found.is_better_than(&b)).unwrap_or(true) {
+                        best = Some(found);
+                    }
+                    break;
+                }
+            }
+        }
+        best
+    }
+
+    /// Same text form as `Field::to_lines`.
+    pub fn to_lines(self) -> Vec<String> {
+        (0..self.height as i32)
+            .map(|y| {
+                (0..self.width as i32)
+                    .map(|x| self.get(x, y).map(|c| c.to_char()).unwrap_or('.'))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BitBoard;
+    use ai::Placement;
+    use color::Color;
+    use field::Field;
+    use position::Position;
+    use poyo::Poyo;
+    use poyopoyo::PoyoPoyo;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+    use size::Size;
+
+    #[test]
+    fn test_too_big() {
+        assert!(BitBoard::new(6, 12).is_some());
+        assert!(BitBoard::new(8, 16).is_some());
+        assert!(BitBoard::new(9, 12).is_none());
+        assert!(BitBoard::new(6, 17).is_none());
+    }
+
+    #[test]
+    fn test_fall_and_pop() {
+        let field = Field::from_lines(&[".RR...", "R.....", "O.....", "RR....", "GGOR.."]).unwrap();
+        let mut board = BitBoard::from_field(&field).unwrap();
+        board.fall();
+        let fallen = ["......", "R.....", "OR....", "RRR...", "GGOR.."];
+        assert_eq!(board.to_lines(), fallen);
+        let chain = board.resolve();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain.removed(), 4);
+        let popped = ["......", "......", "......", "R.....", "GG.R.."];
+        assert_eq!(board.to_lines(), popped);
+    }
+
+    /// Every placement of random pairs on random stacks gives the same
+    /// chain and the same board on both backends.
+    #[test]
+    fn test_same_as_field() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+        for _ in 0..30 {
+            let mut field = Field::with_seed(Size::new(6, 12), rng.gen());
+            for _ in 0..rng.gen_range(0, 40) {
+                let x = rng.gen_range(0, 6);
+                let color = Color::from(rng.gen::<u8>());
+                let color = if rng.gen_range(0, 8) == 0 {
+                    Color::Garbage
+                } else {
+                    color
+                };
+                field.drop_poyo(Poyo::new(Position::new(x, 0), color));
+            }
+            field.resolve();
+            let board = BitBoard::from_field(&field).unwrap();
+            assert_eq!(board.to_lines(), field.to_lines());
+            assert_eq!(board.potential(), field.potential());
+            let pair = PoyoPoyo::rand_from(&mut rng, 4);
+            for placement in Placement::all(6) {
+                let pair = placement.apply(&pair);
+                let (mut f, mut b) = (field.clone(), board);
+                let (fc, bc) = (f.drop_pair(&pair), b.drop_pair(&pair));
+                assert_eq!(fc.is_some(), bc.is_some());
+                if let (Some(fc), Some(bc)) = (fc, bc) {
+                    assert_eq!((fc.len(), fc.score()), (bc.len(), bc.score()));
+                }
+                assert_eq!(f.to_lines(), b.to_lines());
+            }
+        }
+    }
+}
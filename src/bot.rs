@@ -0,0 +1,311 @@
+//! Lets an external program play as one player.
+//!
+//! The bot is spawned with `sh -c CMD` and talks line-delimited JSON over
+//! its stdin and stdout. Whenever a new pair appears the game writes one
+//! line describing the field:
+//!
+//! ```text
+//! {"type":"turn","width":6,"height":12,"field":["......",...,"..RG.."],
+//!  "current":["R","B"],"next":[["G","G"],["Y","R"]],"garbage":0,"score":0}
+//! ```
+//!
+//! `field` lists the rows from the top with `R`, `G`, `Y`, `B` for colors,
+//! `O` for garbage, `H` for hard garbage, `P` for point poyos, `S` for sun
+//! poyos, `W` for wall blocks and `.` for empty cells, without the current
+//! pair.
+//! `current` is the pivot first, then the poyo hanging below it. Larger
+//! pieces list their cells from the top left of their 2x2 box, clockwise,
+//! skipping the empty one of a triple.
+//! `garbage` is what is waiting to fall on this field.
+//!
+//! The bot answers with one line, either a placement or a list of actions:
+//!
+//! ```text
+//! {"x":2,"rotation":1}
+//! {"actions":["left","rotate","down"]}
+//! ```
+//!
+//! `x` is the pivot's column and `rotation` the number of counterclockwise
+//! turns from the spawn orientation: 0 hangs the second poyo below the
+//! pivot, 1 puts it on the right, 2 above and 3 on the left. Larger pieces
+//! are dropped as dealt, `x` being the left column of their box. Once the
+//! actions run out the pair is pushed down until it is fixed.
+//!
+//! Every turn gets exactly one reply. A reply that comes after its pair
+//! was already fixed is skipped rather than applied to the next pair.
+//! Whatever the bot writes to stderr is thrown away.
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use action::Action;
+use ai::Placement;
+use field::Field;
+use piece::Piece;
+use serde_json;
+
+/// Frames between two actions of a bot.
+const INTERVAL: u32 = 2;
+/// How long a blocking bot may think before it is given up on.
+const TIMEOUT: u64 = 10;
+
+#[derive(Serialize)]
+struct Turn {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    width: usize,
+    height: usize,
+    field: Vec<String>,
+    current: Vec<char>,
+    next: Vec<Vec<char>>,
+    garbage: usize,
+    score: usize,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+enum Reply {
+    Placement { x: i32, rotation: usize },
+    Actions { actions: Vec<Action> },
+}
+
+enum Plan {
+    Waiting,
+    Placement(Placement),
+    Actions(VecDeque<Action>),
+}
+
+pub struct Bot {
+    command: String,
+    child: Child,
+    stdin: ChildStdin,
+    rx: Receiver<String>,
+    plan: Option<Plan>,
+    /// Replies still owed for turns whose pair was fixed meanwhile.
+    stale: usize,
+    wait: u32,
+    blocking: bool,
+    error: Option<String>,
+}
+
+impl Bot {
+    pub fn spawn(command: &str) -> Result<Bot, String> {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("cannot run bot `{}`: {}", command, e))?;
+        let stdin = child.stdin.take().ok_or("bot has no stdin")?;
+        let stdout = child.stdout.take().ok_or("bot has no stdout")?;
+        let (tx, rx) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                match line {
+                    Ok(line) => {
+                        if tx.send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            }
+        });
+        Ok(Bot {
+            command: command.to_string(),
+            child,
+            stdin,
+            rx,
+            plan: None,
+            stale: 0,
+            wait: 0,
+            blocking: false,
+            error: None,
+        })
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// The first protocol error, after which the bot is no longer asked.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Makes the game wait for each reply instead of letting the pair fall
+    /// meanwhile, so that a headless match does not depend on how fast
+    /// the bot answers.
+    pub fn set_blocking(&mut self, blocking: bool) {
+        self.blocking = blocking;
+    }
+
+    /// Forgets the plan; called whenever a new pair appears. The reply
+    /// to a turn still awaited is skipped when it comes.
+    pub fn reset(&mut self) {
+        if let Some(Plan::Waiting) = self.plan.take() {
+            self.stale += 1;
+        }
+    }
+
+    pub fn action(&mut self, field: &Field, garbage: usize, score: usize) -> Option<Action> {
+        field.current()?;
+        if self.error.is_some() {
+            return None;
+        }
+        if self.plan.is_none() {
+            if let Err(e) = self.send(field, garbage, score) {
+                self.error = Some(e);
+                return None;
+            }
+            self.plan = Some(Plan::Waiting);
+        }
+        while let Some(Plan::Waiting) = self.plan {
+            let timeout = if self.blocking {
+                Duration::from_secs(TIMEOUT)
+            } else {
+                Duration::from_secs(0)
+            };
+            match self.rx.recv_timeout(timeout) {
+                Ok(_) if self.stale > 0 => self.stale -= 1,
+                Ok(line) => match Bot::parse(&line, field) {
+                    Ok(plan) => self.plan = Some(plan),
+                    Err(e) => {
+                        self.error = Some(e);
+                        return None;
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.blocking {
+                        self.error = Some(format!("bot `{}` timed out", self.command));
+                    }
+                    return None;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.error = Some(format!("bot `{}` exited", self.command));
+                    return None;
+                }
+            }
+        }
+        if self.wait > 0 {
+            self.wait -= 1;
+            return None;
+        }
+        self.wait = INTERVAL;
+        match self.plan {
+            Some(Plan::Placement(target)) => Some(target.step(field)),
+            Some(Plan::Actions(ref mut actions)) => actions.pop_front().or(Some(Action::Down)),
+            _ => None,
+        }
+    }
+
+    fn send(&mut self, field: &Field, garbage: usize, score: usize) -> Result<(), String> {
+        let piece = |p: &Piece| p.poyos().iter().map(|p| p.color().to_char()).collect();
+        let turn = Turn {
+            kind: "turn",
+            width: field.width(),
+            height: field.height(),
+            field: field.simulate().to_lines(),
+            current: field
+                .current()
+                .map(&piece)
+                .unwrap_or_else(|| vec!['.', '.']),
+            next: field.next().iter().map(&piece).collect(),
+            garbage,
+            score,
+        };
+        let line = serde_json::to_string(&turn).map_err(|e| e.to_string())?;
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("cannot write to bot `{}`: {}", self.command, e))
+    }
+
+    fn parse(line: &str, field: &Field) -> Result<Plan, String> {
+        let reply: Reply =
+            serde_json::from_str(line).map_err(|e| format!("bad reply {:?}: {}", line, e))?;
+        match reply {
+            Reply::Placement { x, rotation } => {
+                let placement = Placement::new(x, rotation);
+                if Placement::all(field.width()).contains(&placement) {
+                    Ok(Plan::Placement(placement))
+                } else {
+                    Err(format!("placement out of the field: {:?}", placement))
+                }
+            }
+            Reply::Actions { actions } => Ok(Plan::Actions(actions.into_iter().collect())),
+        }
+    }
+}
+
+impl Drop for Bot {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Bot, Reply};
+    use action::Action;
+    use field::Field;
+    use serde_json;
+    use std::time::{Duration, Instant};
+
+    /// The first action `bot` takes on `field`, failing on a bot error or
+    /// if none comes within a few seconds.
+    fn first_action(bot: &mut Bot, field: &Field) -> Action {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(action) = bot.action(field, 0, 0) {
+                return action;
+            }
+            assert_eq!(bot.error(), None);
+            assert!(Instant::now() < deadline, "the bot did not answer");
+        }
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        let reply: Reply = serde_json::from_str(r#"{"x":2,"rotation":1}"#).unwrap();
+        assert_eq!(reply, Reply::Placement { x: 2, rotation: 1 });
+        let reply: Reply = serde_json::from_str(r#"{"actions":["left","rotate"]}"#).unwrap();
+        assert_eq!(
+            reply,
+            Reply::Actions {
+                actions: vec![Action::Left, Action::Rotate]
+            }
+        );
+        assert!(Bot::parse(r#"{"x":5,"rotation":1}"#, &Field::default()).is_err());
+        assert!(Bot::parse("pass", &Field::default()).is_err());
+    }
+
+    #[test]
+    fn test_bot_moves_pair() {
+        let mut bot = Bot::spawn("while read l; do echo '{\"x\":4,\"rotation\":0}'; done").unwrap();
+        let mut field = Field::default();
+        field.spawn();
+        assert_eq!(first_action(&mut bot, &field), Action::Right);
+        assert_eq!(bot.error(), None);
+    }
+
+    #[test]
+    fn test_late_reply_is_skipped() {
+        let mut bot = Bot::spawn(
+            "read l; sleep 0.2; echo '{\"x\":0,\"rotation\":0}'; \
+             while read l; do echo '{\"x\":4,\"rotation\":0}'; done",
+        )
+        .unwrap();
+        let mut field = Field::default();
+        field.spawn();
+        bot.action(&field, 0, 0);
+        bot.reset();
+        assert_eq!(first_action(&mut bot, &field), Action::Right);
+    }
+}
@@ -0,0 +1,165 @@
+use color::Color;
+
+const CHAIN_POWER: [usize; 19] = [
+    0, 8, 16, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448, 480, 512,
+];
+const COLOR_BONUS: [usize; 6] = [0, 0, 3, 6, 12, 24];
+/// Score of each point poyo cleared.
+pub const POINT_SCORE: usize = 100;
+/// Garbage sent for each sun poyo cleared.
+pub const SUN_GARBAGE: usize = 3;
+
+fn group_bonus(size: usize) -> usize {
+    match size {
+        0..=4 => 0,
+        5..=10 => size - 3,
+        _ => 10,
+    }
+}
+
+/// Groups popped at once during one step of a chain.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChainStep {
+    groups: Vec<(Color, usize)>,
+    /// Point poyos cleared next to the groups.
+    points: usize,
+    /// Sun poyos cleared next to the groups.
+    suns: usize,
+}
+
+impl ChainStep {
+    pub fn push(&mut self, color: Color, size: usize) {
+        self.groups.push((color, size));
+    }
+
+    /// Counts a poyo of `color` cleared next to the groups; only point
+    /// and sun poyos are worth anything.
+    pub fn push_cleared(&mut self, color: Color) {
+        match color {
+            Color::Point => self.points += 1,
+            Color::Sun => self.suns += 1,
+            _ => {}
+        }
+    }
+
+    pub fn suns(&self) -> usize {
+        self.suns
+    }
+
+    pub fn groups(&self) -> &[(Color, usize)] {
+        &self.groups
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.groups.iter().map(|&(_, size)| size).sum()
+    }
+
+    pub fn colors(&self) -> usize {
+        let mut colors: Vec<Color> = vec![];
+        for &(color, _) in &self.groups {
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+        colors.len()
+    }
+
+    /// Score of this step when it is the `nth` link (1-origin) of a chain.
+    pub fn score(&self, nth: usize) -> usize {
+        let power = CHAIN_POWER[nth.saturating_sub(1).min(CHAIN_POWER.len() - 1)];
+        let color = COLOR_BONUS[self.colors().min(COLOR_BONUS.len() - 1)];
+        let group: usize = self.groups.iter().map(|&(_, size)| group_bonus(size)).sum();
+        let bonus = (power + color + group).clamp(1, 999);
+        10 * self.removed() * bonus + POINT_SCORE * self.points
+    }
+}
+
+/// Everything that popped after a pair was fixed, step by step.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Chain {
+    steps: Vec<ChainStep>,
+    all_clear: bool,
+}
+
+impl Chain {
+    pub fn push(&mut self, step: ChainStep) {
+        self.steps.push(step);
+    }
+
+    /// Marks the chain as having left the field empty.
+    pub fn set_all_clear(&mut self) {
+        self.all_clear = true;
+    }
+
+    pub fn is_all_clear(&self) -> bool {
+        self.all_clear
+    }
+
+    pub fn steps(&self) -> &[ChainStep] {
+        &self.steps
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn removed(&self) -> usize {
+        self.steps.iter().map(|s| s.removed()).sum()
+    }
+
+    /// Garbage the sun poyos cleared add to what the chain sends.
+    pub fn sun_garbage(&self) -> usize {
+        self.steps.iter().map(|s| s.suns()).sum::<usize>() * SUN_GARBAGE
+    }
+
+    pub fn score(&self) -> usize {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s.score(i + 1))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Chain, ChainStep};
+    use color::Color;
+
+    #[test]
+    fn test_single_group_score() {
+        let mut step = ChainStep::default();
+        step.push(Color::Red, 4);
+        assert_eq!(step.score(1), 40);
+    }
+
+    #[test]
+    fn test_chain_score() {
+        let mut chain = Chain::default();
+        for _ in 0..2 {
+            let mut step = ChainStep::default();
+            step.push(Color::Red, 4);
+            chain.push(step);
+        }
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain.removed(), 8);
+        assert_eq!(chain.score(), 40 + 40 * 8);
+    }
+
+    #[test]
+    fn test_color_and_group_bonus() {
+        let mut step = ChainStep::default();
+        step.push(Color::Red, 5);
+        step.push(Color::Blue, 4);
+        assert_eq!(step.colors(), 2);
+        assert_eq!(step.score(1), 10 * 9 * (3 + 2));
+    }
+}
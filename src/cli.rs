@@ -1,8 +1,29 @@
+use std::collections::HashSet;
+
 use color::Color;
+use editor::{self, Editor, Focus};
+use fever::GAUGE;
+use field::Field;
+use game::Game;
 use game_state::GameState;
+use layout::{Block, Layout, Rect};
+use libc;
+use menu::Menu;
 use ncurses::*;
+use player::Player;
+use position::Position;
 use poyo::Poyo;
+use race;
+use screen::{Board, Screen};
 use size::Size;
+use theme::{color_number, Attr, Border, Depth, Theme};
+
+/// First color number redefined when the terminal takes RGB values.
+const TRUECOLOR_SLOT: i16 = 16;
+/// Color pairs of the ghost piece start after those of the poyos.
+const GHOST_PAIR: i16 = 16;
+/// Keys of practice, shown before it starts.
+const PRACTICE_KEYS: [&str; 4] = ["z undo", "y redo", "r reroll", "n choose"];
 
 // このCliViewはゲーム画面の描画用なんだよなぁ
 // これを流用して他の画面作れるか？Elm的なアーキテクチャじゃないとだるいな
@@ -10,102 +31,712 @@ use size::Size;
 // 描画だけするものと状態を渡すものに分けたいな
 // GameStateとstate内の状態を受け取って描画する方向に変更するのはどうか
 pub struct CliView {
-    max_size: Size,
-    size: Size,
-    win: WINDOW,
+    depth: Depth,
+    theme: Theme,
+    field: Size,
+    players: usize,
+    layout: Option<Layout>,
+    windows: Vec<Windows>,
 }
 
-impl CliView {
-    pub fn new(size: Size) -> Self {
-        let mut max_width = 0;
-        let mut max_height = 0;
-        getmaxyx(stdscr(), &mut max_height, &mut max_width);
-        let max_size = Size::new(max_width as usize, max_height as usize);
-        let win: WINDOW = newwin(14, 8, 0, 0);
+/// The windows of one player, created from a `Block` of the layout.
+struct Windows {
+    well: WINDOW,
+    next: WINDOW,
+    score: WINDOW,
+    level: WINDOW,
+    chain: WINDOW,
+    garbage: WINDOW,
+    stats: Option<WINDOW>,
+}
+
+impl Windows {
+    fn new(block: &Block) -> Self {
+        Windows {
+            well: Windows::window(&block.well),
+            next: Windows::window(&block.next),
+            score: Windows::window(&block.score),
+            level: Windows::window(&block.level),
+            chain: Windows::window(&block.chain),
+            garbage: Windows::window(&block.garbage),
+            stats: block.stats.as_ref().map(Windows::window),
+        }
+    }
+
+    fn window(rect: &Rect) -> WINDOW {
+        newwin(rect.height, rect.width, rect.y, rect.x)
+    }
 
+    fn delete(&self) {
+        for &win in &[
+            self.well,
+            self.next,
+            self.score,
+            self.level,
+            self.chain,
+            self.garbage,
+        ] {
+            delwin(win);
+        }
+        if let Some(win) = self.stats {
+            delwin(win);
+        }
+    }
+}
+
+impl CliView {
+    /// A view of `players` wells of `field` cells each.
+    pub fn new(field: Size, players: usize, theme: Theme) -> Self {
         CliView {
-            max_size,
-            size,
-            win,
+            depth: Depth::Mono,
+            theme,
+            field,
+            players,
+            layout: None,
+            windows: vec![],
         }
     }
+
     pub fn init(&mut self) {
+        set_locale();
         initscr();
-        if !has_colors() {
-            endwin();
-            panic!("terminal does not support colors");
+        if has_colors() {
+            start_color();
+            use_default_colors();
         }
-        start_color();
         raw();
         noecho();
         keypad(stdscr(), true);
         curs_set(CURSOR_VISIBILITY::CURSOR_INVISIBLE);
-        init_pair(Color::Red as i16, 0, 1);
-        init_pair(Color::Green as i16, 0, 2);
-        init_pair(Color::Yellow as i16, 0, 3);
-        init_pair(Color::Blue as i16, 0, 4);
+        self.depth = Depth::detect(has_colors(), COLORS(), can_change_color());
+        self.init_colors();
+        refresh();
+        self.resize();
+    }
+
+    /// One color pair per `Color` from the theme's swatches. Monochrome
+    /// terminals get none and fall back to glyphs and attributes.
+    fn init_colors(&self) {
+        if self.depth == Depth::Mono {
+            return;
+        }
+        for &color in &Color::every() {
+            let swatch = self.theme.swatch(color);
+            let slot = TRUECOLOR_SLOT + 2 * color as i16;
+            if self.depth == Depth::TrueColor {
+                for &(rgb, slot) in &[(swatch.fg, slot), (swatch.bg, slot + 1)] {
+                    if let Some(rgb) = rgb {
+                        let (r, g, b) = rgb.scaled();
+                        init_color(slot, r, g, b);
+                    }
+                }
+            }
+            let fg = color_number(swatch.fg, self.depth, slot);
+            let bg = color_number(swatch.bg, self.depth, slot + 1);
+            init_pair(color as i16, fg, bg);
+            init_pair(GHOST_PAIR + color as i16, if bg < 0 { fg } else { bg }, -1);
+        }
+    }
+
+    /// Shows `players` wells of `field` cells from now on.
+    pub fn set_players(&mut self, field: Size, players: usize) {
+        self.field = field;
+        self.players = players;
+        self.resize();
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.init_colors();
+    }
+
+    /// Lays the windows out again for the current terminal size. Called on
+    /// start and whenever ncurses reports `KEY_RESIZE`.
+    pub fn resize(&mut self) {
+        for windows in self.windows.drain(..) {
+            windows.delete();
+        }
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(stdscr(), &mut height, &mut width);
+        let screen = Size::new(width.max(0) as usize, height.max(0) as usize);
+        self.layout = Layout::new(&screen, &self.field, self.players);
+        if let Some(ref layout) = self.layout {
+            self.windows = layout.blocks.iter().map(Windows::new).collect();
+        }
+        clear();
         refresh();
-        self.win = newwin(14, 8, 0, 0);
-    }
-
-    pub fn draw(&self, state: &GameState) {
-        wmove(self.win, 0, 0);
-        wclear(self.win);
-        match state {
-            GameState::Start => {
-                mvwprintw(self.win, 5, 1, "press");
-                mvwprintw(self.win, 6, 1, "space");
-                mvwprintw(self.win, 7, 1, "to");
-                mvwprintw(self.win, 8, 1, "start");
-                box_(self.win, '|' as u32, ' ' as u32);
-            }
-            GameState::Playing { ref poyos } => {
-                for row in poyos.iter() {
-                    for col in row.iter() {
-                        if let &Some(p) = col {
-                            self.print(&p);
+    }
+
+    pub fn draw(&self, screen: &Screen, game: &Game) {
+        match *screen {
+            Screen::Menu(_, ref menu) => self.draw_menu(menu),
+            Screen::Game(ref state) => self.draw_game(state, game),
+            Screen::Summary => self.draw_summary(game),
+            Screen::Scores(ref board) => self.draw_scores(board),
+            Screen::Editor(ref editor) => self.draw_editor(editor),
+        }
+    }
+
+    /// The board being edited with the cursor on it, the pairs in the next
+    /// window and the keys beside, if there is room.
+    fn draw_editor(&self, editor: &Editor) {
+        let cell_width = match self.layout {
+            Some(ref layout) => layout.cell_width,
+            None => return self.draw_too_small(),
+        };
+        let windows = &self.windows[0];
+        let win = windows.well;
+        werase(win);
+        self.draw_field(win, cell_width, editor.board());
+        if editor.focus() == Focus::Board {
+            let cursor = editor.cursor();
+            let (x, y) = CliView::translate(cursor.x, cursor.y, cell_width);
+            self.print_cursor(
+                win,
+                y,
+                x,
+                cell_width,
+                editor.board()[cursor].map(|p| p.color()),
+            );
+        }
+        self.draw_border(win, &self.theme.well);
+        wrefresh(win);
+        self.draw_pairs(windows.next, cell_width, editor);
+        let gravity = if editor.gravity() { "on" } else { "off" };
+        self.draw_panel(windows.score, "gravity", gravity);
+        self.draw_panel(windows.level, "goal", &format!("{}-chain", editor.goal()));
+        self.draw_panel(windows.chain, "pairs", &editor.pairs().len().to_string());
+        let cursor = editor.cursor();
+        self.draw_panel(
+            windows.garbage,
+            "cell",
+            &format!("{},{}", cursor.x, cursor.y),
+        );
+        if let Some(win) = windows.stats {
+            werase(win);
+            self.draw_border(win, &self.theme.panel);
+            mvwprintw(win, 0, 1, "keys");
+            let (mut width, mut height) = (0, 0);
+            getmaxyx(win, &mut height, &mut width);
+            let inside = (width - 2).max(0) as usize;
+            let lines = editor::KEYS.iter().take((height - 2).max(0) as usize);
+            for (y, line) in lines.enumerate() {
+                mvwprintw(win, 1 + y as i32, 1, &line[..line.len().min(inside)]);
+            }
+            wrefresh(win);
+        }
+        let height = getmaxy(stdscr());
+        mv(height - 1, 0);
+        clrtoeol();
+        if let Some(ref message) = editor.message {
+            mvprintw(height - 1, 1, message);
+        }
+        refresh();
+    }
+
+    /// The pairs drawn so far, pivot on top, from the one under the cursor
+    /// on; a blank slot after the last one takes a new pair.
+    fn draw_pairs(&self, win: WINDOW, cell_width: i32, editor: &Editor) {
+        werase(win);
+        self.draw_border(win, &self.theme.panel);
+        mvwprintw(win, 0, 1, "pairs");
+        let pairs = editor.pairs();
+        let (first, cursor) = match editor.focus() {
+            Focus::Pairs(i, half) => (i.saturating_sub(1), Some((i, half))),
+            Focus::Board => (pairs.len().saturating_sub(2), None),
+        };
+        let mut x = 1;
+        for i in first..=pairs.len() {
+            if x + cell_width >= getmaxx(win) {
+                break;
+            }
+            let colors = pairs.get(i).map(|&(pivot, child)| [pivot, child]);
+            for half in 0..2 {
+                let color = colors.map(|c| c[half]);
+                let y = 1 + half as i32;
+                if cursor == Some((i, half)) {
+                    self.print_cursor(win, y, x, cell_width, color);
+                } else if let Some(color) = color {
+                    self.print_at(win, y, x, cell_width, color);
+                }
+            }
+            x += cell_width + 1;
+        }
+        wrefresh(win);
+    }
+
+    /// A cell of `color`, or an empty one, in reverse video.
+    fn print_cursor(&self, win: WINDOW, y: i32, x: i32, cell_width: i32, color: Option<Color>) {
+        match color {
+            Some(color) => {
+                let text = self.theme.swatch(color).text(cell_width);
+                self.print_text(win, y, x, color, &text, A_REVERSE());
+            }
+            None => {
+                wattron(win, A_REVERSE());
+                mvwprintw(win, y, x, &" ".repeat(cell_width as usize));
+                wattroff(win, A_REVERSE());
+            }
+        }
+    }
+
+    /// A high score table centered on the screen, the new record
+    /// highlighted.
+    fn draw_scores(&self, board: &Board) {
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(stdscr(), &mut height, &mut width);
+        let rules = board.tables.get(board.table).cloned().unwrap_or_default();
+        let title = if board.tables.len() > 1 {
+            format!("< {} >", rules)
+        } else {
+            rules
+        };
+        let mut lines = vec![format!(
+            "    {:<12} {:>7} {:>5} {:>6}  {:<10} {}",
+            "name", "score", "chain", "time", "date", "seed"
+        )];
+        for (i, entry) in board.entries.iter().enumerate() {
+            let name = match board.typing {
+                Some(ref name) if board.highlight == Some(i) => format!("{}_", name),
+                _ => entry.name.clone(),
+            };
+            lines.push(format!(
+                "{:>2}. {:<12} {:>7} {:>5} {:>6}  {} {}",
+                i + 1,
+                name,
+                entry.score,
+                entry.max_chain,
+                entry.time(),
+                entry.day(),
+                entry.seed
+            ));
+        }
+        let wide = lines.iter().map(|l| l.len()).max().unwrap_or(0) as i32;
+        let x = ((width - wide) / 2).max(0);
+        let mut y = ((height - lines.len() as i32 - 6) / 2).max(0);
+        let center = |text: &str| ((width - text.chars().count() as i32) / 2).max(0);
+        erase();
+        attron(A_BOLD());
+        mvprintw(y, center("high scores"), "high scores");
+        attroff(A_BOLD());
+        mvprintw(y + 1, center(&title), &title);
+        y += 3;
+        for (i, line) in lines.iter().enumerate() {
+            let attr = if i > 0 && board.highlight == Some(i - 1) {
+                A_REVERSE()
+            } else {
+                A_NORMAL()
+            };
+            attron(attr);
+            mvprintw(y + i as i32, x, line);
+            attroff(attr);
+        }
+        if let Some(ref message) = board.message {
+            mvprintw(y + lines.len() as i32 + 1, center(message), message);
+        }
+        refresh();
+    }
+
+    /// The stats of every player side by side, centered on the screen.
+    fn draw_summary(&self, game: &Game) {
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(stdscr(), &mut height, &mut width);
+        let frames = game.frame();
+        let mut columns: Vec<Vec<(String, String)>> = game
+            .players()
+            .iter()
+            .map(|p| {
+                let mut lines = vec![("score".to_string(), p.score().to_string())];
+                lines.extend(p.stats().lines(frames));
+                lines
+            })
+            .collect();
+        if let (Some(race), Some(column)) = (game.race(), columns.first_mut()) {
+            column.extend(race.split_lines(game.splits()));
+        }
+        let labels = columns
+            .iter()
+            .max_by_key(|c| c.len())
+            .cloned()
+            .unwrap_or_default();
+        let mut lines = vec![];
+        if columns.len() > 1 {
+            let mut header = format!("{:<10}", "");
+            for i in 0..columns.len() {
+                header += &format!(" {:>9}", format!("player {}", i + 1));
+            }
+            lines.push(header);
+        }
+        for (i, (label, _)) in labels.iter().enumerate() {
+            let mut line = format!("{:<10}", label);
+            for column in &columns {
+                let value = column.get(i).map(|l| l.1.as_str()).unwrap_or("0");
+                line += &format!(" {:>9}", value);
+            }
+            lines.push(line);
+        }
+        let secs = frames / 100;
+        let title = format!("game stats, {}:{:02}", secs / 60, secs % 60);
+        let message = "enter to go on";
+        let wide = lines.iter().map(|l| l.len()).max().unwrap_or(0) as i32;
+        let x = ((width - wide) / 2).max(0);
+        let mut y = ((height - lines.len() as i32 - 4) / 2).max(0);
+        let center = |text: &str| ((width - text.len() as i32) / 2).max(0);
+        erase();
+        attron(A_BOLD());
+        mvprintw(y, center(&title), &title);
+        attroff(A_BOLD());
+        y += 2;
+        for (i, line) in lines.iter().enumerate() {
+            mvprintw(y + i as i32, x, line);
+        }
+        mvprintw(y + lines.len() as i32 + 1, center(message), message);
+        refresh();
+    }
+
+    /// The menu centered on the screen, the entry under the cursor
+    /// highlighted.
+    fn draw_menu(&self, menu: &Menu) {
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(stdscr(), &mut height, &mut width);
+        let lines: Vec<String> = menu.entries().iter().map(|e| e.text()).collect();
+        let wide = lines
+            .iter()
+            .chain(menu.message.iter())
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0) as i32;
+        let x = ((width - wide) / 2).max(0);
+        let mut y = ((height - lines.len() as i32 - 4) / 2).max(0);
+        erase();
+        attron(A_BOLD());
+        mvprintw(
+            y,
+            ((width - menu.title.len() as i32) / 2).max(0),
+            &menu.title,
+        );
+        attroff(A_BOLD());
+        y += 2;
+        for (i, line) in lines.iter().enumerate() {
+            let attr = if i == menu.cursor() {
+                A_REVERSE()
+            } else {
+                A_NORMAL()
+            };
+            attron(attr);
+            mvprintw(y + i as i32, x, line);
+            attroff(attr);
+        }
+        if let Some(ref message) = menu.message {
+            mvprintw(y + lines.len() as i32 + 1, x, message);
+        }
+        refresh();
+    }
+
+    fn draw_game(&self, state: &GameState, game: &Game) {
+        let cell_width = match self.layout {
+            Some(ref layout) => layout.cell_width,
+            None => return self.draw_too_small(),
+        };
+        for (i, player) in game.players().iter().enumerate() {
+            let win = self.windows[i].well;
+            werase(win);
+            match *state {
+                GameState::Start => {
+                    mvwprintw(win, 5, 1, "press");
+                    mvwprintw(win, 6, 1, "space");
+                    mvwprintw(win, 7, 1, "to");
+                    mvwprintw(win, 8, 1, "start");
+                    if let Some(objective) = game.objective() {
+                        mvwprintw(win, 10, 1, "goal");
+                        mvwprintw(win, 11, 1, &objective.short());
+                    } else if player.practice().is_some() {
+                        for (y, line) in PRACTICE_KEYS.iter().enumerate() {
+                            mvwprintw(win, 9 + y as i32, 1, line);
                         }
                     }
                 }
-                box_(self.win, '|' as u32, ' ' as u32);
+                GameState::Countdown(left) => {
+                    self.draw_field(win, cell_width, player.field());
+                    self.draw_banner(win, &((left + 99) / 100).to_string());
+                }
+                GameState::Playing => {
+                    self.draw_field(win, cell_width, player.field());
+                    if let Some(banner) = player.banner() {
+                        self.draw_banner(win, banner);
+                    }
+                }
+                GameState::GameOver => {
+                    if let Some(cleared) = game.is_cleared() {
+                        mvwprintw(win, 5, 1, if cleared { "Clear!" } else { "Failed" });
+                    } else if game.race().is_some_and(|r| r.is_up(game.frame())) {
+                        mvwprintw(win, 4, 2, "Time");
+                        mvwprintw(win, 5, 2, "Up");
+                    } else if game.players().len() < 2 {
+                        mvwprintw(win, 4, 2, "Game");
+                        mvwprintw(win, 5, 2, "Over");
+                    } else if game.winner() == Some(i) {
+                        mvwprintw(win, 5, 2, "Win");
+                    } else {
+                        mvwprintw(win, 5, 2, "Lose");
+                    }
+                    if i == 0 {
+                        mvwprintw(win, 7, 1, "press");
+                        mvwprintw(win, 8, 1, "enter");
+                    }
+                }
             }
-            GameState::GameOver => {
-                mvwprintw(self.win, 4, 2, "Game");
-                mvwprintw(self.win, 5, 2, "Over");
-                box_(self.win, '|' as u32, ' ' as u32);
+            self.draw_border(win, &self.theme.well);
+            wrefresh(win);
+            let clock = game.race().map(|r| r.clock(game.frame()));
+            self.draw_info(&self.windows[i], cell_width, player, clock);
+            if let Some(win) = self.windows[i].stats {
+                self.draw_stats(win, player, game.frame());
             }
         }
-        wrefresh(self.win);
     }
 
-    fn print(&self, poyo: &Poyo) {
-        let (x, y) = (poyo.x(), poyo.y());
-        let (x, y) = CliView::translate(x, y);
-        let color = poyo.color();
-        let s = match color {
-            Color::Red => "*",
-            Color::Green => "+",
-            Color::Yellow => "@",
-            Color::Blue => "#",
+    /// `text` in bold across the top of the well, a word a line if it
+    /// does not fit on one.
+    fn draw_banner(&self, win: WINDOW, text: &str) {
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(win, &mut height, &mut width);
+        let lines: Vec<&str> = if text.len() as i32 <= width - 2 {
+            vec![text]
+        } else {
+            text.split_whitespace().collect()
+        };
+        wattron(win, A_BOLD());
+        for (i, line) in lines.iter().enumerate() {
+            let x = ((width - line.len() as i32) / 2).max(1);
+            mvwprintw(win, 2 + i as i32, x, line);
+        }
+        wattroff(win, A_BOLD());
+    }
+
+    /// The next pieces and the panels of `player`; `clock` replaces the
+    /// level in a game against the clock.
+    fn draw_info(&self, windows: &Windows, cell_width: i32, player: &Player, clock: Option<u64>) {
+        let win = windows.next;
+        werase(win);
+        self.draw_border(win, &self.theme.panel);
+        mvwprintw(win, 0, 1, "next");
+        let mut x = 1;
+        for piece in player.field().next() {
+            let grid = piece.grid();
+            let width = grid.first().map_or(0, |row| row.len()) as i32 * cell_width;
+            if x + width >= getmaxx(win) {
+                break;
+            }
+            for (y, row) in grid.iter().take(2).enumerate() {
+                for (dx, c) in row.iter().enumerate() {
+                    if let Some(color) = *c {
+                        let cx = x + dx as i32 * cell_width;
+                        self.print_at(win, 1 + y as i32, cx, cell_width, color);
+                    }
+                }
+            }
+            x += width + 1;
+        }
+        wrefresh(win);
+        self.draw_panel(windows.score, "score", &player.score().to_string());
+        let (label, text) = match (clock, player.practice(), player.fever()) {
+            (Some(clock), _, _) => ("time", race::time(clock)),
+            (None, Some(practice), _) => {
+                let placed = practice.undos() + practice.redos();
+                ("undo", format!("{}/{}", practice.undos(), placed))
+            }
+            (None, None, Some(fever)) if fever.is_active() => {
+                let time = fever.time();
+                ("fever", format!("{}.{}s", time / 100, time % 100 / 10))
+            }
+            (None, None, Some(fever)) => ("gauge", format!("{}/{}", fever.gauge(), GAUGE)),
+            (None, None, None) => ("level", player.level().to_string()),
         };
-        wattron(self.win, COLOR_PAIR(color as i16));
-        mvwprintw(self.win, y, x, s);
-        wattroff(self.win, COLOR_PAIR(color as i16));
+        self.draw_panel(windows.level, label, &text);
+        self.draw_panel(windows.chain, "chain", &player.chain().to_string());
+        self.draw_panel(windows.garbage, "garbage", "");
+        if player.garbage() > 0 {
+            let win = windows.garbage;
+            self.print_at(win, 1, 1, 1, Color::Garbage);
+            mvwprintw(win, 1, 3, &player.garbage().to_string());
+            wrefresh(win);
+        }
     }
 
-    fn translate(x: i32, y: i32) -> (i32, i32) {
-        (x + 1, y + 1)
+    /// As many stats as fit, labels on the left and values on the right.
+    fn draw_stats(&self, win: WINDOW, player: &Player, frames: u64) {
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(win, &mut height, &mut width);
+        werase(win);
+        self.draw_border(win, &self.theme.panel);
+        mvwprintw(win, 0, 1, "stats");
+        let inside = (width - 2).max(0) as usize;
+        let lines = player.stats().lines(frames);
+        for (y, (label, value)) in lines.iter().take((height - 2).max(0) as usize).enumerate() {
+            let pad = inside.saturating_sub(label.len());
+            mvwprintw(
+                win,
+                y as i32 + 1,
+                1,
+                &format!("{}{:>2$}", label, value, pad),
+            );
+        }
+        wrefresh(win);
+    }
+
+    /// A boxed panel with its title on the top border.
+    fn draw_panel(&self, win: WINDOW, title: &str, value: &str) {
+        werase(win);
+        self.draw_border(win, &self.theme.panel);
+        mvwprintw(win, 0, 1, title);
+        mvwprintw(win, 1, 1, value);
+        wrefresh(win);
+    }
+
+    fn draw_too_small(&self) {
+        let minimum = Layout::minimum(&self.field, self.players);
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(stdscr(), &mut height, &mut width);
+        let lines = [
+            "terminal too small".to_string(),
+            format!("need {}x{}", minimum.width, minimum.height),
+        ];
+        erase();
+        for (i, line) in lines.iter().enumerate() {
+            let x = (width - line.len() as i32).max(0) / 2;
+            mvprintw(height / 2 + i as i32 - 1, x, line);
+        }
+        refresh();
+    }
+
+    /// The poyos of `field`, joined into groups if the theme can draw them
+    /// so. Groups of three or more, one poyo away from popping, are bold.
+    fn draw_field(&self, win: WINDOW, cell_width: i32, field: &Field) {
+        self.draw_ghost(win, cell_width, field);
+        let joined = match self.theme.joined {
+            Some(ref joined) => joined,
+            None => {
+                for p in field.rows().iter().flatten().flatten() {
+                    self.print(win, cell_width, p);
+                }
+                return;
+            }
+        };
+        let growing: HashSet<Position> = field
+            .simulate()
+            .groups()
+            .into_iter()
+            .filter(|g| g.1.len() >= 3)
+            .flat_map(|g| g.1)
+            .collect();
+        for p in field.rows().iter().flatten().flatten() {
+            let pos = Position::new(p.x(), p.y());
+            let (x, y) = CliView::translate(p.x(), p.y(), cell_width);
+            let text = joined.text(field.links(pos), cell_width);
+            let extra = if growing.contains(&pos) {
+                A_BOLD()
+            } else {
+                A_NORMAL()
+            };
+            self.print_text(win, y, x, p.color(), &text, extra);
+        }
+    }
+
+    /// Dim outlines of where the current piece will land, drawn in the
+    /// poyo's color on the well's background.
+    fn draw_ghost(&self, win: WINDOW, cell_width: i32, field: &Field) {
+        if let Some(landing) = field.landing() {
+            for p in &landing {
+                let swatch = self.theme.swatch(p.color());
+                let attr = if self.depth == Depth::Mono {
+                    swatch.mono.iter().fold(A_DIM(), |a, &b| a | to_attr(b))
+                } else {
+                    A_DIM() | COLOR_PAIR(GHOST_PAIR + p.color() as i16)
+                };
+                let text = match self.theme.ghost.as_str() {
+                    "" => swatch.text(cell_width),
+                    ghost => ghost.repeat(cell_width as usize),
+                };
+                let (x, y) = CliView::translate(p.x(), p.y(), cell_width);
+                wattron(win, attr);
+                mvwprintw(win, y, x, &text);
+                wattroff(win, attr);
+            }
+        }
+    }
+
+    fn print(&self, win: WINDOW, cell_width: i32, poyo: &Poyo) {
+        let (x, y) = CliView::translate(poyo.x(), poyo.y(), cell_width);
+        self.print_at(win, y, x, cell_width, poyo.color());
+    }
+
+    fn print_at(&self, win: WINDOW, y: i32, x: i32, cell_width: i32, color: Color) {
+        let text = self.theme.swatch(color).text(cell_width);
+        self.print_text(win, y, x, color, &text, A_NORMAL());
+    }
+
+    /// `text` in the colors of `color`, with `extra` attributes.
+    fn print_text(&self, win: WINDOW, y: i32, x: i32, color: Color, text: &str, extra: attr_t) {
+        let swatch = self.theme.swatch(color);
+        let mut attr = swatch.attrs.iter().fold(extra, |a, &b| a | to_attr(b));
+        if self.depth == Depth::Mono {
+            attr = swatch.mono.iter().fold(attr, |a, &b| a | to_attr(b));
+        } else {
+            attr |= COLOR_PAIR(color as i16);
+        }
+        wattron(win, attr);
+        mvwprintw(win, y, x, text);
+        wattroff(win, attr);
+    }
+
+    fn draw_border(&self, win: WINDOW, border: &Border) {
+        let (mut width, mut height) = (0, 0);
+        getmaxyx(win, &mut height, &mut width);
+        let horizontal = border.horizontal.repeat((width - 2).max(0) as usize);
+        mvwprintw(
+            win,
+            0,
+            0,
+            &format!("{}{}{}", border.top_left, horizontal, border.top_right),
+        );
+        for y in 1..height - 1 {
+            mvwprintw(win, y, 0, &border.vertical);
+            mvwprintw(win, y, width - 1, &border.vertical);
+        }
+        // Writing the bottom right corner scrolls the window, so it is
+        // inserted rather than printed.
+        mvwprintw(
+            win,
+            height - 1,
+            0,
+            &format!("{}{}", border.bottom_left, horizontal),
+        );
+        mvwinsstr(win, height - 1, width - 1, &border.bottom_right);
+    }
+
+    fn translate(x: i32, y: i32, cell_width: i32) -> (i32, i32) {
+        (x * cell_width + 1, y + 1)
     }
 
     pub fn exit(&self) {
         endwin();
     }
+}
 
-    pub fn width(&self) -> usize {
-        self.size.width
+fn to_attr(attr: Attr) -> attr_t {
+    match attr {
+        Attr::Bold => A_BOLD(),
+        Attr::Underline => A_UNDERLINE(),
+        Attr::Reverse => A_REVERSE(),
+        Attr::Dim => A_DIM(),
     }
+}
 
-    pub fn height(&self) -> usize {
-        self.size.height
+/// Takes the locale from the environment so UTF-8 glyphs are drawn.
+/// `ncurses::setlocale` hands C a string that is already freed.
+fn set_locale() {
+    unsafe {
+        libc::setlocale(libc::LC_ALL, b"\0".as_ptr() as *const libc::c_char);
     }
 }
@@ -7,6 +7,15 @@ pub enum Color {
     Green = 2,
     Yellow = 3,
     Blue = 4,
+    Garbage = 5,
+    /// Garbage that turns into plain garbage when first cleared.
+    Hard = 6,
+    /// Clears like garbage and scores `chain::POINT_SCORE`.
+    Point = 7,
+    /// Clears like garbage and sends `chain::SUN_GARBAGE` more.
+    Sun = 8,
+    /// A wall cell that never falls nor clears.
+    Block = 9,
 }
 
 impl From<u8> for Color {
@@ -25,4 +34,65 @@ impl Color {
     pub fn rand() -> Self {
         Color::from(random::<u8>())
     }
+
+    /// Letter used for this color in the text form of a field.
+    pub fn to_char(self) -> char {
+        match self {
+            Color::Red => 'R',
+            Color::Green => 'G',
+            Color::Yellow => 'Y',
+            Color::Blue => 'B',
+            Color::Garbage => 'O',
+            Color::Hard => 'H',
+            Color::Point => 'P',
+            Color::Sun => 'S',
+            Color::Block => 'W',
+        }
+    }
+
+    pub fn from_char(c: char) -> Option<Color> {
+        match c {
+            'R' => Some(Color::Red),
+            'G' => Some(Color::Green),
+            'Y' => Some(Color::Yellow),
+            'B' => Some(Color::Blue),
+            'O' => Some(Color::Garbage),
+            'H' => Some(Color::Hard),
+            'P' => Some(Color::Point),
+            'S' => Some(Color::Sun),
+            'W' => Some(Color::Block),
+            _ => None,
+        }
+    }
+
+    /// Colors that pop in groups; garbage only clears next to them.
+    pub fn all() -> [Color; 4] {
+        [Color::Red, Color::Green, Color::Yellow, Color::Blue]
+    }
+
+    /// Every kind of cell, the colors first.
+    pub fn every() -> [Color; 9] {
+        [
+            Color::Red,
+            Color::Green,
+            Color::Yellow,
+            Color::Blue,
+            Color::Garbage,
+            Color::Hard,
+            Color::Point,
+            Color::Sun,
+            Color::Block,
+        ]
+    }
+
+    /// Whether poyos of this color pop in groups of four.
+    pub fn pops(self) -> bool {
+        Color::all().contains(&self)
+    }
+
+    /// Whether this clears when a neighbouring group pops. Hard garbage
+    /// only softens into garbage.
+    pub fn clears(self) -> bool {
+        matches!(self, Color::Garbage | Color::Point | Color::Sun)
+    }
 }
@@ -0,0 +1,318 @@
+//! The field editor: a cursor paints any cell, the pairs to deal are drawn
+//! beside the board, and the board is saved in the field text form or as
+//! a puzzle, played from, or has its chain simulated.
+
+use std::mem;
+
+use color::Color;
+use field::Field;
+use game::Game;
+use ncurses::{KEY_BACKSPACE, KEY_DC, KEY_DOWN, KEY_ENTER, KEY_LEFT, KEY_RIGHT, KEY_UP};
+use player::{Controller, Player};
+use position::Position;
+use poyo::Poyo;
+use poyopoyo::PoyoPoyo;
+use puzzle::{Objective, Puzzle};
+use size::Size;
+
+/// What the keys do, as shown beside the board.
+pub const KEYS: [&str; 13] = [
+    "rgyb colors",
+    "o h garbage",
+    "p s w special",
+    ". clear",
+    "tab pairs",
+    "v gravity",
+    "x simulate",
+    "u undo sim",
+    "- + goal",
+    "f save field",
+    "e save puzzle",
+    "enter play",
+    "esc back",
+];
+
+/// Where the cursor is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Focus {
+    Board,
+    /// A pair, the pivot (0) or child (1) of it. One past the last pair
+    /// appends a new one.
+    Pairs(usize, usize),
+}
+
+/// What the editor leaves to the caller.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Request {
+    Play,
+    SaveField,
+    SavePuzzle,
+    Back,
+}
+
+pub struct Editor {
+    board: Field,
+    cursor: Position,
+    focus: Focus,
+    /// Pivot and child of each pair to deal, in order.
+    pairs: Vec<(Color, Color)>,
+    /// Whether painted poyos fall to the stack.
+    gravity: bool,
+    /// Chain a saved puzzle has to fire.
+    goal: usize,
+    /// The board before the chain last simulated.
+    before: Option<Field>,
+    /// Shown under the board, for results and errors.
+    pub message: Option<String>,
+}
+
+impl Editor {
+    /// An empty board of `size`, the cursor at the bottom left.
+    pub fn new(size: Size) -> Self {
+        Editor {
+            cursor: Position::new(0, size.height as i32 - 1),
+            board: Field::new(size),
+            focus: Focus::Board,
+            pairs: vec![],
+            gravity: false,
+            goal: 2,
+            before: None,
+            message: None,
+        }
+    }
+
+    pub fn board(&self) -> &Field {
+        &self.board
+    }
+
+    pub fn cursor(&self) -> Position {
+        self.cursor
+    }
+
+    pub fn focus(&self) -> Focus {
+        self.focus
+    }
+
+    pub fn pairs(&self) -> &[(Color, Color)] {
+        &self.pairs
+    }
+
+    pub fn gravity(&self) -> bool {
+        self.gravity
+    }
+
+    pub fn goal(&self) -> usize {
+        self.goal
+    }
+
+    pub fn on_key(&mut self, key: i32) -> Option<Request> {
+        match key {
+            KEY_LEFT => self.move_cursor(-1, 0),
+            KEY_RIGHT => self.move_cursor(1, 0),
+            KEY_UP => self.move_cursor(0, -1),
+            KEY_DOWN => self.move_cursor(0, 1),
+            0x09 => {
+                self.focus = match self.focus {
+                    Focus::Board => Focus::Pairs(self.pairs.len(), 0),
+                    Focus::Pairs(..) => Focus::Board,
+                }
+            }
+            0x76 => self.toggle_gravity(),
+            0x78 => self.simulate(),
+            0x75 => self.undo_simulation(),
+            0x2d => self.goal = (self.goal - 1).max(1),
+            0x2b | 0x3d => self.goal += 1,
+            0x66 => return Some(Request::SaveField),
+            0x65 => return Some(Request::SavePuzzle),
+            KEY_ENTER | 0x0a | 0x0d => return Some(Request::Play),
+            0x1b => return Some(Request::Back),
+            0x2e | KEY_BACKSPACE | KEY_DC | 0x7f => self.paint(None),
+            0x21..=0x7e => {
+                let c = (key as u8 as char).to_ascii_uppercase();
+                if let Some(color) = Color::from_char(c) {
+                    self.paint(Some(color));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Moves over the board, or over the pairs: left and right between
+    /// pivot and child, up and down between pairs.
+    fn move_cursor(&mut self, dx: i32, dy: i32) {
+        match self.focus {
+            Focus::Board => {
+                let x = (self.cursor.x + dx).clamp(0, self.board.width() as i32 - 1);
+                let y = (self.cursor.y + dy).clamp(0, self.board.height() as i32 - 1);
+                self.cursor = Position::new(x, y);
+            }
+            Focus::Pairs(i, half) => {
+                let i = (i as i32 + dy).clamp(0, self.pairs.len() as i32) as usize;
+                let half = (half as i32 + dx).clamp(0, 1) as usize;
+                self.focus = Focus::Pairs(i, half);
+            }
+        }
+    }
+
+    /// Sets the cell under the cursor, or empties it with `None`. Pairs
+    /// only take colors that pop; emptying one removes the pair.
+    pub fn paint(&mut self, color: Option<Color>) {
+        match self.focus {
+            Focus::Board => {
+                let pos = self.cursor;
+                self.board[pos] = color.map(|c| Poyo::new(pos, c));
+                if self.gravity {
+                    self.board.fall_poyos();
+                }
+            }
+            Focus::Pairs(i, half) => match color {
+                Some(color) if !color.pops() => {
+                    self.message = Some("pairs are made of colors".to_string());
+                }
+                Some(color) if i == self.pairs.len() => self.pairs.push((color, color)),
+                Some(color) if half == 0 => self.pairs[i].0 = color,
+                Some(color) => self.pairs[i].1 = color,
+                None if i < self.pairs.len() => {
+                    self.pairs.remove(i);
+                }
+                None => {}
+            },
+        }
+    }
+
+    /// Turns gravity on or off; on, everything falls at once.
+    pub fn toggle_gravity(&mut self) {
+        self.gravity = !self.gravity;
+        if self.gravity {
+            self.board.fall_poyos();
+        }
+    }
+
+    /// Pops what is ready to pop, keeping the board as it was to undo.
+    pub fn simulate(&mut self) {
+        let mut board = self.board.clone();
+        let chain = board.resolve();
+        self.message = Some(if chain.is_empty() {
+            "nothing pops".to_string()
+        } else {
+            format!("{}-chain, {} points", chain.len(), chain.score())
+        });
+        self.before = Some(mem::replace(&mut self.board, board));
+    }
+
+    pub fn undo_simulation(&mut self) {
+        if let Some(before) = self.before.take() {
+            self.board = before;
+        }
+    }
+
+    /// The board in the field text form.
+    pub fn lines(&self) -> Vec<String> {
+        self.board.to_lines()
+    }
+
+    /// The board as a puzzle named `name`: the pairs drawn have to fire a
+    /// chain of the goal. Empty rows on top are left out.
+    pub fn puzzle(&self, name: &str) -> Result<Puzzle, String> {
+        if self.pairs.is_empty() {
+            return Err("draw the pairs of the puzzle first".to_string());
+        }
+        let mut field = self.lines();
+        while field.len() > 1 && field[0].chars().all(|c| c == '.') {
+            field.remove(0);
+        }
+        let pairs = self
+            .pairs
+            .iter()
+            .map(|&(pivot, child)| format!("{}{}", pivot.to_char(), child.to_char()))
+            .collect();
+        Ok(Puzzle {
+            name: name.to_string(),
+            field,
+            pairs,
+            goal: Objective::Chain(self.goal),
+        })
+    }
+
+    /// A game from the board: the pairs drawn and the goal, or random
+    /// pairs without an end if none were drawn.
+    pub fn game(&self) -> Result<Game, String> {
+        let lines = self.lines();
+        let lines: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+        let mut field = Field::from_lines(&lines)?;
+        if self.pairs.is_empty() {
+            return Ok(Game::new(vec![Player::new(field, Controller::Human)]));
+        }
+        let pairs = self.pairs.iter().map(|&(p, c)| PoyoPoyo::spawned(p, c));
+        field.set_pairs(pairs.collect());
+        let game = Game::new(vec![Player::new(field, Controller::Human)]);
+        Ok(game.with_objective(Objective::Chain(self.goal)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Editor, Focus, Request};
+    use color::Color;
+    use ncurses::{KEY_DOWN, KEY_LEFT, KEY_RIGHT, KEY_UP};
+    use puzzle::Objective;
+    use size::Size;
+
+    fn keys(editor: &mut Editor, keys: &str) {
+        for c in keys.chars() {
+            editor.on_key(c as i32);
+        }
+    }
+
+    #[test]
+    fn test_paint_and_simulate() {
+        let mut editor = Editor::new(Size::new(4, 4));
+        keys(&mut editor, "r");
+        editor.on_key(KEY_UP);
+        keys(&mut editor, "r");
+        editor.on_key(KEY_UP);
+        editor.on_key(KEY_UP);
+        keys(&mut editor, "rv");
+        assert!(editor.gravity());
+        assert_eq!(editor.lines(), vec!["....", "R...", "R...", "R..."]);
+        editor.on_key(KEY_UP);
+        keys(&mut editor, "r");
+        assert_eq!(editor.lines()[0], "R...");
+        keys(&mut editor, "x");
+        assert_eq!(editor.message, Some("1-chain, 40 points".to_string()));
+        assert!(editor.board().is_empty());
+        keys(&mut editor, "u");
+        assert_eq!(editor.lines()[0], "R...");
+        keys(&mut editor, ".");
+        assert_eq!(editor.lines()[0], "....");
+    }
+
+    #[test]
+    fn test_pairs_make_a_puzzle() {
+        let mut editor = Editor::new(Size::new(6, 12));
+        keys(&mut editor, "o");
+        assert!(editor.puzzle("empty").is_err());
+        keys(&mut editor, "\tr");
+        assert_eq!(editor.focus(), Focus::Pairs(0, 0));
+        editor.on_key(KEY_RIGHT);
+        keys(&mut editor, "bo");
+        assert_eq!(editor.pairs(), &[(Color::Red, Color::Blue)]);
+        assert!(editor.message.is_some());
+        editor.on_key(KEY_DOWN);
+        keys(&mut editor, "g");
+        editor.on_key(KEY_UP);
+        editor.on_key(KEY_LEFT);
+        keys(&mut editor, ".");
+        assert_eq!(editor.pairs(), &[(Color::Green, Color::Green)]);
+        keys(&mut editor, "+");
+        let puzzle = editor.puzzle("mine").unwrap();
+        assert_eq!(puzzle.field, vec!["O....."]);
+        assert_eq!(puzzle.pairs, vec!["GG"]);
+        assert_eq!(puzzle.goal, Objective::Chain(3));
+        assert_eq!(puzzle.board().unwrap().height(), 12);
+        let game = editor.game().unwrap();
+        assert_eq!(game.objective(), Some(Objective::Chain(3)));
+        assert_eq!(editor.on_key(0x0a), Some(Request::Play));
+    }
+}
@@ -0,0 +1,251 @@
+//! Fever rules. Offsetting garbage fills a gauge; once it is full the
+//! board is put aside for a pre-built chain pattern and a countdown
+//! starts. Every chain fired adds time and deals the next pattern, one
+//! link longer if the last was fired in full and one shorter if not.
+//! Once time runs out the fever ends at the next lock, which gravity
+//! brings soon, and the board comes back as it was. Fever rules also
+//! deal triples, quads and big pieces besides pairs.
+//!
+//! Patterns are kept in the field text form, top row first:
+//!
+//! ```json
+//! [{ "chain": 3, "field": [".GY...", ".RGY..", ".RGY..", ".RGY.."] }]
+//! ```
+
+use chain::Chain;
+use color::Color;
+use field::{Field, PoyoRows};
+use piece::Shape;
+use serde_json;
+
+/// Offsets that fill the gauge.
+pub const GAUGE: usize = 7;
+/// Frames a fever lasts without chains.
+const TIME: u32 = 1500;
+/// Frames added by each link of a chain fired in fever.
+const TIME_PER_LINK: u32 = 50;
+/// Longest a fever can be made to last.
+const MAX_TIME: u32 = 3000;
+/// Length of the first pattern, and of the shortest.
+const START_SIZE: usize = 3;
+/// Shapes dealt by fever rules, pairs twice as often as the rest.
+pub const SHAPES: [Shape; 5] = [
+    Shape::Pair,
+    Shape::Pair,
+    Shape::Triple,
+    Shape::Quad,
+    Shape::Big,
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pattern {
+    pub chain: usize,
+    pub field: Vec<String>,
+}
+
+impl Pattern {
+    pub fn builtin() -> Vec<Pattern> {
+        serde_json::from_str(include_str!("../puzzles/fever.json"))
+            .expect("the fever patterns are valid")
+    }
+
+    /// Whether the pattern can be dealt on `field`, leaving the spawn
+    /// rows free and using only the colors it deals.
+    fn fits(&self, field: &Field) -> bool {
+        let colors = &Color::all()[..field.colors()];
+        self.field.len() + 2 <= field.height()
+            && self.field.iter().all(|line| {
+                line.chars().count() <= field.width()
+                    && line
+                        .chars()
+                        .filter_map(Color::from_char)
+                        .all(|c| colors.contains(&c))
+            })
+    }
+
+    /// The poyos of the pattern on an empty board of `width` by `height`.
+    pub fn board(&self, width: usize, height: usize) -> Result<PoyoRows, String> {
+        let mut lines = vec![".".repeat(width); height.saturating_sub(self.field.len())];
+        for line in &self.field {
+            let pad = width.saturating_sub(line.chars().count());
+            lines.push(format!("{}{}", line, ".".repeat(pad)));
+        }
+        let lines: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+        Ok(Field::from_lines(&lines)?.rows().clone())
+    }
+}
+
+#[derive(Clone)]
+pub struct Fever {
+    gauge: usize,
+    /// Frames left, while the fever lasts.
+    time: u32,
+    /// Length of the next pattern.
+    size: usize,
+    /// Patterns dealt so far, to pick the next among those of a length.
+    dealt: usize,
+    /// The board put aside for the fever.
+    board: Option<PoyoRows>,
+    patterns: Vec<Pattern>,
+}
+
+impl Default for Fever {
+    fn default() -> Self {
+        Fever {
+            gauge: 0,
+            time: 0,
+            size: START_SIZE,
+            dealt: 0,
+            board: None,
+            patterns: Pattern::builtin(),
+        }
+    }
+}
+
+impl Fever {
+    pub fn gauge(&self) -> usize {
+        self.gauge
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.board.is_some()
+    }
+
+    /// Frames left, while the fever lasts.
+    pub fn time(&self) -> u32 {
+        self.time
+    }
+
+    /// Garbage was offset: fills the gauge, and starts the fever once it
+    /// is full.
+    pub fn on_offset(&mut self, field: &mut Field) {
+        if self.is_active() {
+            return;
+        }
+        self.gauge = (self.gauge + 1).min(GAUGE);
+        if self.gauge == GAUGE {
+            if let Some(pattern) = self.deal(field) {
+                self.board = Some(field.swap_board(pattern));
+                self.time = TIME;
+            }
+        }
+    }
+
+    pub fn on_frame(&mut self) {
+        if self.is_active() {
+            self.time = self.time.saturating_sub(1);
+        }
+    }
+
+    /// A pair was locked in fever: a chain adds time and deals the next
+    /// pattern, and once time is up the board comes back. Only a lock ends
+    /// the fever, as the piece still falling was dealt onto the pattern.
+    pub fn on_lock(&mut self, field: &mut Field, chain: &Chain) {
+        if !self.is_active() {
+            return;
+        }
+        if !chain.is_empty() {
+            self.time = (self.time + TIME_PER_LINK * chain.len() as u32).min(MAX_TIME);
+            self.size = if chain.len() >= self.size {
+                self.size + 1
+            } else {
+                (self.size - 1).max(START_SIZE)
+            };
+        }
+        if self.time == 0 {
+            if let Some(board) = self.board.take() {
+                field.swap_board(board);
+            }
+            self.gauge = 0;
+        } else if !chain.is_empty() {
+            if let Some(pattern) = self.deal(field) {
+                field.swap_board(pattern);
+            }
+        }
+    }
+
+    /// The next pattern that fits `field`: the longest up to the current
+    /// length, taking turns between those of the same length.
+    fn deal(&mut self, field: &Field) -> Option<PoyoRows> {
+        let fitting: Vec<&Pattern> = self.patterns.iter().filter(|p| p.fits(field)).collect();
+        let size = fitting
+            .iter()
+            .map(|p| p.chain)
+            .filter(|&c| c <= self.size)
+            .max()?;
+        let same: Vec<&&Pattern> = fitting.iter().filter(|p| p.chain == size).collect();
+        let pattern = same[self.dealt % same.len()];
+        self.dealt += 1;
+        self.size = size;
+        pattern.board(field.width(), field.height()).ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Fever, Pattern, GAUGE};
+    use ai::Placement;
+    use chain::{Chain, ChainStep};
+    use color::Color;
+    use field::Field;
+    use poyopoyo::PoyoPoyo;
+    use size::Size;
+
+    /// The longest chain a single pair fires on `field`.
+    fn best(field: &Field) -> usize {
+        let mut best = 0;
+        for &a in &Color::all() {
+            for &b in &Color::all() {
+                for placement in Placement::all(field.width()) {
+                    let mut sim = field.simulate();
+                    if let Some(chain) = sim.drop_pair(&placement.apply(&PoyoPoyo::spawned(a, b))) {
+                        best = best.max(chain.len());
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_patterns_fire_their_chain() {
+        for pattern in Pattern::builtin() {
+            let lines: Vec<&str> = pattern.field.iter().map(|l| l.as_str()).collect();
+            let mut field = Field::from_lines(&lines).unwrap();
+            assert!(field.resolve().is_empty(), "{:?}", pattern.field);
+            assert_eq!(best(&field), pattern.chain, "{:?}", pattern.field);
+        }
+    }
+
+    #[test]
+    fn test_fever_swaps_the_board() {
+        let mut field = Field::with_colors(Size::new(6, 12), 1, 3);
+        field.drop_garbage(6);
+        field.spawn();
+        let board = field.to_lines();
+        let mut fever = Fever::default();
+        for _ in 1..GAUGE {
+            fever.on_offset(&mut field);
+        }
+        assert!(!fever.is_active());
+        fever.on_offset(&mut field);
+        assert!(fever.is_active());
+        assert_eq!(field.to_lines()[11], ".RGY..");
+        // A 3-chain deals a longer pattern.
+        let mut chain = Chain::default();
+        for _ in 0..3 {
+            let mut step = ChainStep::default();
+            step.push(Color::Red, 4);
+            chain.push(step);
+        }
+        fever.on_lock(&mut field, &chain);
+        assert_eq!(field.to_lines()[11], ".RGYR.");
+        while fever.time() > 0 {
+            fever.on_frame();
+        }
+        fever.on_lock(&mut field, &Chain::default());
+        assert!(!fever.is_active());
+        assert_eq!(fever.gauge(), 0);
+        assert_eq!(field.to_lines(), board);
+    }
+}
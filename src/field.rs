@@ -1,21 +1,65 @@
+use std::collections::VecDeque;
+use std::mem;
 use std::ops::{Index, IndexMut};
 
+use chain::{Chain, ChainStep};
+use color::Color;
 use direction::Direction;
 use direction::Direction::*;
-use event::Event;
+use piece::{Piece, Shape};
 use position::Position;
 use poyo::Poyo;
 use poyopoyo::PoyoPoyo;
+use rand::{thread_rng, Rng, SeedableRng, XorShiftRng};
 use size::Size;
-use std::sync::mpsc::Sender;
 
 pub type PoyoRows = Vec<Vec<Option<Poyo>>>;
 
+/// Number of upcoming pairs shown in the preview.
+const PREVIEW: usize = 2;
+
+/// Directions in which a cell is joined to a same-colored neighbour.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Links(u8);
+
+impl Links {
+    fn bit(direction: Direction) -> u8 {
+        match direction {
+            Up => 1,
+            Right => 2,
+            Down => 4,
+            Left => 8,
+        }
+    }
+
+    pub fn join(&mut self, direction: Direction) {
+        self.0 |= Links::bit(direction);
+    }
+
+    pub fn has(&self, direction: Direction) -> bool {
+        self.0 & Links::bit(direction) != 0
+    }
+
+    /// 0 to 15, with bits for up, right, down and left from the lowest.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Clone)]
 pub struct Field {
     size: Size,
-    current: Option<PoyoPoyo>,
+    current: Option<Piece>,
+    next: VecDeque<Piece>,
     poyos: PoyoRows,
-    tx: Sender<Event>,
+    pieces: XorShiftRng,
+    garbage: XorShiftRng,
+    /// Whether the queue was given and is not refilled when it runs out.
+    scripted: bool,
+    /// How many of `Color::all` the pairs are dealt from.
+    colors: u8,
+    /// Shapes the pieces are dealt from, evenly.
+    shapes: Vec<Shape>,
 }
 
 impl Index<Position> for Field {
@@ -31,154 +75,419 @@ impl IndexMut<Position> for Field {
     }
 }
 
+impl Default for Field {
+    fn default() -> Self {
+        Field::new(Size::new(6, 12))
+    }
+}
+
 impl Field {
-    pub fn new(tx: Sender<Event>, size: Size) -> Self {
+    pub fn new(size: Size) -> Self {
+        Field::with_seed(size, thread_rng().gen())
+    }
+
+    /// A field whose pairs and garbage are drawn from `seed`. Two fields
+    /// with the same seed are dealt the same pairs.
+    pub fn with_seed(size: Size, seed: u32) -> Self {
+        Field::with_colors(size, seed, Color::all().len())
+    }
+
+    /// A seeded field dealing pairs of the first `colors` colors only.
+    pub fn with_colors(size: Size, seed: u32, colors: usize) -> Self {
+        let colors = colors.max(1).min(Color::all().len()) as u8;
         let poyos = vec![vec![None; size.width]; size.height];
+        let mut pieces = XorShiftRng::from_seed([seed, 0x193a_6754, 0xa8a7_d469, 0x9783_0e05]);
+        let garbage = XorShiftRng::from_seed([0x8aeb_d2f4, seed, 0x5c4b_9ce1, 0x2e1f_62a3]);
+        let next = (0..PREVIEW)
+            .map(|_| Piece::rand_from(&mut pieces, colors, &[Shape::Pair]))
+            .collect();
         Field {
             size,
             current: None,
+            next,
             poyos,
-            tx,
+            pieces,
+            garbage,
+            scripted: false,
+            colors,
+            shapes: vec![Shape::Pair],
+        }
+    }
+
+    /// Deals pieces of `shapes` from now on, the preview included.
+    pub fn set_shapes(&mut self, shapes: Vec<Shape>) {
+        self.shapes = shapes;
+        if !self.scripted {
+            self.next = (0..PREVIEW).map(|_| self.deal()).collect();
+        }
+    }
+
+    /// Deals exactly `pairs`, in order, instead of random ones. Once they
+    /// are used up `spawn` fails, as if the field had topped out.
+    pub fn set_pairs(&mut self, pairs: Vec<PoyoPoyo>) {
+        self.next = pairs.into_iter().map(Piece::from).collect();
+        self.scripted = true;
+    }
+
+    /// Puts `piece` in place of the next one.
+    pub fn set_next<P: Into<Piece>>(&mut self, piece: P) {
+        match self.next.front_mut() {
+            Some(next) => *next = piece.into(),
+            None => self.next.push_back(piece.into()),
         }
     }
 
+    /// Deals another piece in place of the next one.
+    pub fn reroll_next(&mut self) {
+        let piece = self.deal();
+        self.set_next(piece);
+    }
+
     pub fn move_current(&mut self, d: Direction) {
         if self.current_can_move(&d) {
             if let Some(mut c) = self.current.take() {
-                let (x1, x2) = c.x();
-                let (y1, y2) = c.y();
-                self.poyos[y1 as usize][x1 as usize] = None;
-                self.poyos[y2 as usize][x2 as usize] = None;
+                self.remove(&c);
                 c.moves(d);
                 self.current = Some(c);
                 self.update_field();
-                self.update();
             }
         }
     }
 
     pub fn rotate_current(&mut self) {
-        if self.current_can_rotate() {
-            if let Some(mut c) = self.current.take() {
-                let (x1, x2) = c.x();
-                let (y1, y2) = c.y();
-                self.poyos[y1 as usize][x1 as usize] = None;
-                self.poyos[y2 as usize][x2 as usize] = None;
-                c.rotate();
-                self.current = Some(c);
-                self.update_field();
-                self.update();
+        if let Some(rotated) = self.rotated_current() {
+            if let Some(c) = self.current.take() {
+                self.remove(&c);
             }
+            self.current = Some(rotated);
+            self.update_field();
         }
     }
 
-    fn current_can_rotate(&self) -> bool {
-        if let Some(ref current) = self.current {
-            let pos = current.rotated_position();
-            let res = !self.is_filled(pos.x, pos.y);
-            res
-        } else {
-            false
+    pub fn current_can_rotate(&self) -> bool {
+        self.rotated_current().is_some()
+    }
+
+    /// The current piece turned, if it can be. Pieces in a box are kicked
+    /// a column aside when turning in place is blocked.
+    fn rotated_current(&self) -> Option<Piece> {
+        let current = self.current.as_ref()?;
+        let rotated = current.rotated(self.colors());
+        if let Some(pair) = current.pair() {
+            let pos = pair.rotated_position();
+            return if self.is_filled(pos.x, pos.y) {
+                None
+            } else {
+                Some(rotated)
+            };
+        }
+        for kick in &[None, Some(Left), Some(Right)] {
+            let mut piece = rotated.clone();
+            if let Some(d) = *kick {
+                piece.moves(d);
+            }
+            if self.fits(&piece) {
+                return Some(piece);
+            }
+        }
+        None
+    }
+
+    /// Whether `piece` fits over the board, the current piece aside.
+    fn fits(&self, piece: &Piece) -> bool {
+        piece.poyos().iter().all(|p| {
+            let pos = Position::new(p.x(), p.y());
+            !self.is_filled(p.x(), p.y()) || self.is_current(pos)
+        })
+    }
+
+    /// Clears the cells of `piece` from the board.
+    fn remove(&mut self, piece: &Piece) {
+        for p in piece.poyos() {
+            self.poyos[p.y() as usize][p.x() as usize] = None;
+        }
+    }
+
+    fn deal(&mut self) -> Piece {
+        Piece::rand_from(&mut self.pieces, self.colors, &self.shapes)
+    }
+
+    pub fn fix_current(&mut self) -> Option<(Chain, (i32, i32))> {
+        let fixed = self.lock_current();
+        if fixed.is_some() {
+            self.spawn();
         }
+        fixed
     }
 
-    pub fn fix_current(&mut self) -> Option<(usize, (i32, i32))> {
+    /// Fixes the current pair and resolves the chain it fires, leaving the
+    /// field without a current pair until `spawn` is called.
+    pub fn lock_current(&mut self) -> Option<(Chain, (i32, i32))> {
         if let Some(c) = self.current.take() {
-            self.fall_poyos();
-            let mut removed_count = 0;
-            loop {
-                let removed = self.check();
-                removed_count += removed;
-                if removed <= 0 {
-                    break;
-                }
-                self.fall_poyos();
+            let chain = self.resolve();
+            Some((chain, (c.left(), c.top())))
+        } else {
+            None
+        }
+    }
+
+    /// Takes the next piece from the queue as the current one.
+    /// Returns false if the spawn cells are already filled, or if a
+    /// scripted queue is empty.
+    pub fn spawn(&mut self) -> bool {
+        let piece = match self.next.pop_front() {
+            Some(piece) => piece,
+            None if self.scripted => return false,
+            None => self.deal(),
+        };
+        if !self.scripted {
+            let next = self.deal();
+            self.next.push_back(next);
+        }
+        if piece.poyos().iter().any(|p| self.is_filled(p.x(), p.y())) {
+            return false;
+        }
+        self.set_current(piece);
+        true
+    }
+
+    /// Lets everything fall and pops groups until nothing is left to pop,
+    /// noting whether the chain emptied the field.
+    pub fn resolve(&mut self) -> Chain {
+        let mut chain = Chain::default();
+        self.fall_poyos();
+        loop {
+            let step = self.check();
+            if step.is_empty() {
+                break;
             }
-            self.current = Some(PoyoPoyo::rand());
-            self.update_field();
-            self.update();
-            Some((removed_count, (c.left(), c.top())))
+            chain.push(step);
+            self.fall_poyos();
+        }
+        if !chain.is_empty() && self.is_empty() {
+            chain.set_all_clear();
+        }
+        chain
+    }
+
+    /// Puts `poyo` on top of the stack in its column without resolving.
+    /// Returns false if the column is full.
+    pub fn drop_poyo(&mut self, poyo: Poyo) -> bool {
+        let x = poyo.x();
+        if x < self.leftend() || self.rightend() <= x {
+            return false;
+        }
+        let y = self.drop_row(x);
+        if y < self.top() {
+            return false;
+        }
+        let mut poyo = poyo;
+        let pos = Position::new(x, y);
+        poyo.update_position(pos);
+        self[pos] = Some(poyo);
+        true
+    }
+
+    /// Drops `pair` straight down onto the stack and resolves the chain it
+    /// fires, without spawning. Meant for simulation on a `simulate` copy.
+    pub fn drop_pair(&mut self, pair: &PoyoPoyo) -> Option<Chain> {
+        let (lower, upper) = if pair.0.y() >= pair.1.y() {
+            (pair.0, pair.1)
+        } else {
+            (pair.1, pair.0)
+        };
+        if self.drop_poyo(lower) && self.drop_poyo(upper) {
+            Some(self.resolve())
         } else {
             None
         }
     }
 
-    fn update(&self) {
-        let _ = self.tx.send(Event::MovePoyo(self.poyos.clone()));
+    /// Drops `piece` straight down like `drop_pair`, the lowest poyos first.
+    pub fn drop_piece(&mut self, piece: &Piece) -> Option<Chain> {
+        let mut poyos = piece.poyos();
+        poyos.sort_by_key(|p| -p.y());
+        if poyos.into_iter().all(|p| self.drop_poyo(p)) {
+            Some(self.resolve())
+        } else {
+            None
+        }
     }
 
-    pub fn on_init(&self) {
-        self.update();
+    /// The poyos of the current piece where they would land if dropped
+    /// now, in the order of `Piece::poyos`. Each falls onto its own column,
+    /// the lowest first. `None` without a piece or when a poyo would not fit.
+    pub fn landing(&self) -> Option<Vec<Poyo>> {
+        let current = self.current.as_ref()?;
+        let mut sim = self.simulate();
+        let mut land = |poyo: Poyo| {
+            let y = sim.drop_row(poyo.x());
+            if !sim.drop_poyo(poyo) {
+                return None;
+            }
+            let mut poyo = poyo;
+            poyo.update_position(Position::new(poyo.x(), y));
+            Some(poyo)
+        };
+        let poyos = current.poyos();
+        let mut order: Vec<usize> = (0..poyos.len()).collect();
+        order.sort_by_key(|&i| -poyos[i].y());
+        let mut landed = poyos.clone();
+        for i in order {
+            landed[i] = land(poyos[i])?;
+        }
+        Some(landed)
     }
 
-    pub fn set_current(&mut self, v: PoyoPoyo) {
-        self.current = Some(v.clone());
-        let (x1, x2) = v.x();
-        let (y1, y2) = v.y();
-        self.poyos[y1 as usize][x1 as usize] = Some(v.0);
-        self.poyos[y2 as usize][x2 as usize] = Some(v.1);
+    /// Drops `count` garbage poyos: whole rows first, the rest into random
+    /// columns. Garbage that does not fit is lost.
+    pub fn drop_garbage(&mut self, count: usize) {
+        let width = self.width();
+        let mut columns: Vec<i32> = (0..width as i32).collect();
+        self.garbage.shuffle(&mut columns);
+        for i in 0..count {
+            let x = columns[i % width];
+            self.drop_poyo(Poyo::new(Position::new(x, 0), Color::Garbage));
+        }
     }
 
-    pub fn update_field(&mut self) {
-        let mut new_field = vec![vec![None; self.size.width]; self.size.height];
-        for ps in self.poyos.clone() {
-            for p in ps {
-                if let Some(p) = p {
-                    new_field[p.y() as usize][p.x() as usize] = Some(p);
+    /// A copy of the settled board without the current pair, for looking ahead.
+    pub fn simulate(&self) -> Field {
+        let mut field = self.clone();
+        if let Some(c) = field.current.take() {
+            field.remove(&c);
+        }
+        field
+    }
+
+    /// Builds a field from its text form: one line per row from the top,
+    /// a color letter per poyo and `.` for an empty cell.
+    pub fn from_lines(lines: &[&str]) -> Result<Field, String> {
+        let width = lines.first().map(|l| l.chars().count()).unwrap_or(0);
+        if width == 0 {
+            return Err("field is empty".to_string());
+        }
+        let mut field = Field::new(Size::new(width, lines.len()));
+        for (y, line) in lines.iter().enumerate() {
+            if line.chars().count() != width {
+                return Err(format!("row {} is not {} cells wide", y, width));
+            }
+            for (x, c) in line.chars().enumerate() {
+                if c == '.' {
+                    continue;
                 }
+                let color = Color::from_char(c).ok_or(format!("unknown cell: {}", c))?;
+                let pos = Position::new(x as i32, y as i32);
+                field[pos] = Some(Poyo::new(pos, color));
+            }
+        }
+        Ok(field)
+    }
+
+    /// The text form read by `from_lines`, the current pair included.
+    pub fn to_lines(&self) -> Vec<String> {
+        self.poyos
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|c| c.map(|p| p.color().to_char()).unwrap_or('.'))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Puts `board` in place of the poyos, keeping the current pair, and
+    /// returns the poyos it replaced.
+    pub fn swap_board(&mut self, board: PoyoRows) -> PoyoRows {
+        let mut old = mem::replace(&mut self.poyos, board);
+        if let Some(ref c) = self.current {
+            for p in c.poyos() {
+                old[p.y() as usize][p.x() as usize] = None;
             }
         }
-        if let Some(p) = self.current.clone() {
-            let (x1, x2) = p.x();
-            let (y1, y2) = p.y();
-            new_field[y1 as usize][x1 as usize] = Some(p.0.clone());
-            new_field[y2 as usize][x2 as usize] = Some(p.1.clone());
+        self.update_field();
+        old
+    }
+
+    pub fn set_current<P: Into<Piece>>(&mut self, v: P) {
+        let v = v.into();
+        for p in v.poyos() {
+            self.poyos[p.y() as usize][p.x() as usize] = Some(p);
+        }
+        self.current = Some(v);
+    }
+
+    pub fn current(&self) -> Option<&Piece> {
+        self.current.as_ref()
+    }
+
+    pub fn next(&self) -> &VecDeque<Piece> {
+        &self.next
+    }
+
+    pub fn rows(&self) -> &PoyoRows {
+        &self.poyos
+    }
+
+    pub fn update_field(&mut self) {
+        let mut new_field = vec![vec![None; self.size.width]; self.size.height];
+        for p in self.poyos.iter().flatten().flatten() {
+            new_field[p.y() as usize][p.x() as usize] = Some(*p);
+        }
+        if let Some(ref c) = self.current {
+            for p in c.poyos() {
+                new_field[p.y() as usize][p.x() as usize] = Some(p);
+            }
         }
         self.poyos = new_field;
     }
 
+    /// Whether no poyo is left, the current pair and blocks aside.
+    pub fn is_empty(&self) -> bool {
+        let current = self.current.as_ref().map(|c| c.poyos());
+        self.poyos
+            .iter()
+            .flatten()
+            .flatten()
+            .all(|p| p.color() == Color::Block || current.as_ref().is_some_and(|c| c.contains(p)))
+    }
+
     pub fn width(&self) -> usize {
         self.size.width
     }
 
+    /// Number of colors pairs are dealt from.
+    pub fn colors(&self) -> usize {
+        self.colors as usize
+    }
+
     pub fn height(&self) -> usize {
         self.size.height
     }
 
+    /// Row a poyo dropped into column `x` comes to rest in, right above
+    /// the highest cell filled. Above the top when the column is full.
+    fn drop_row(&self, x: i32) -> i32 {
+        let floor = (self.top()..self.bottom()).find(|&y| self.is_filled(x, y));
+        floor.unwrap_or_else(|| self.bottom()) - 1
+    }
+
+    /// Number of poyos in column `x`, the current pair included.
+    pub fn column_height(&self, x: i32) -> usize {
+        self.poyos
+            .iter()
+            .filter(|row| row[x as usize].is_some())
+            .count()
+    }
+
     pub fn current_can_move(&self, d: &Direction) -> bool {
-        if let Some(ref current) = self.current {
-            match d {
-                &Left => {
-                    let (y1, y2) = current.y();
-                    let x = current.left() - 1;
-                    let is_leftend = x < self.leftend();
-                    if is_leftend {
-                        return false;
-                    }
-                    !(self.is_filled(x, y1) || self.is_filled(x, y2))
-                }
-                &Right => {
-                    let (y1, y2) = current.y();
-                    let x = current.right() + 1;
-                    let is_rightend = self.rightend() <= x;
-                    if is_rightend {
-                        return false;
-                    }
-                    !(self.is_filled(x, y1) || self.is_filled(x, y2))
-                }
-                &Down => {
-                    let (x1, x2) = current.x();
-                    let y = current.bottom() + 1;
-                    let is_bottom = self.bottom() <= y;
-                    if is_bottom {
-                        return false;
-                    }
-                    !(self.is_filled(x1, y) || self.is_filled(x2, y))
-                }
-                _ => false,
+        match (self.current.as_ref(), *d) {
+            (Some(current), Left) | (Some(current), Right) | (Some(current), Down) => {
+                let mut moved = current.clone();
+                moved.moves(*d);
+                self.fits(&moved)
             }
-        } else {
-            false
+            _ => false,
         }
     }
 
@@ -225,15 +534,26 @@ impl Field {
         self.poyos = res;
     }
 
+    /// Moves the poyos of each transposed row to its end. Blocks stay put
+    /// and split the row into parts that settle on their own.
     fn right_align(&mut self) {
-        let mut res = Vec::with_capacity(self.poyos.len());
-        for row in &self.poyos {
-            let somes: Vec<&Option<Poyo>> = row.iter().filter(|c| c.is_some()).collect();
-            let mut new_row = vec![None; row.len() - somes.len()];
-            new_row.extend(somes);
-            res.push(new_row);
+        for row in &mut self.poyos {
+            let walls: Vec<usize> = (0..row.len())
+                .filter(|&i| row[i].is_some_and(|p| p.color() == Color::Block))
+                .chain(Some(row.len()))
+                .collect();
+            let mut start = 0;
+            for end in walls {
+                let part = &mut row[start..end];
+                let somes: Vec<Option<Poyo>> =
+                    part.iter().filter(|c| c.is_some()).cloned().collect();
+                let gap = part.len() - somes.len();
+                for (i, cell) in part.iter_mut().enumerate() {
+                    *cell = if i < gap { None } else { somes[i - gap] };
+                }
+                start = end + 1;
+            }
         }
-        self.poyos = res;
     }
 
     fn reset_position(&mut self) {
@@ -241,7 +561,7 @@ impl Field {
         for (y, row) in self.poyos.iter().enumerate() {
             let mut new_row = vec![];
             for (x, col) in row.iter().enumerate() {
-                if let &Some(mut p) = col {
+                if let Some(mut p) = *col {
                     let position = Position::new(x as i32, y as i32);
                     p.update_position(position);
                     new_row.push(Some(p));
@@ -254,23 +574,123 @@ impl Field {
         self.poyos = res;
     }
 
-    pub fn check(&mut self) -> usize {
-        let poyos = self.poyos.clone();
-        let mut removed = 0;
-        for row in poyos.iter() {
-            for col in row.iter() {
-                if let &Some(v) = col {
-                    let (count, counted) = v.count_same_color(&self);
-                    if count >= 4 {
-                        removed += count;
-                        for pos in counted {
-                            self[pos] = None;
+    /// Pops every group of four or more, then clears the garbage and
+    /// special poyos next to them. Hard garbage next to a pop only softens,
+    /// however many poyos popped around it.
+    pub fn check(&mut self) -> ChainStep {
+        let mut step = ChainStep::default();
+        let mut around = vec![];
+        for (color, group) in self.groups() {
+            if group.len() >= 4 {
+                step.push(color, group.len());
+                for pos in group {
+                    self[pos] = None;
+                    for p in &[pos.left(), pos.right(), pos.up(), pos.down()] {
+                        if self.is_inside(*p) && !around.contains(p) {
+                            around.push(*p);
+                        }
+                    }
+                }
+            }
+        }
+        for pos in around {
+            self.clear_nuisance(pos, &mut step);
+        }
+        step
+    }
+
+    /// Groups of same-colored poyos connected up, down, left or right, in
+    /// the order of their first cell from the top left. Garbage and the
+    /// special poyos never form a group. Unlike `Poyo::count_same_color`
+    /// this is a flood fill over a flat visited table, cheap enough to run
+    /// in searches.
+    pub fn groups(&self) -> Vec<(Color, Vec<Position>)> {
+        let (width, height) = (self.width(), self.height());
+        let mut seen = vec![false; width * height];
+        let mut groups = vec![];
+        let mut stack = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                let color = match self.poyos[y][x] {
+                    Some(p) if p.color().pops() && !seen[y * width + x] => p.color(),
+                    _ => continue,
+                };
+                let mut group = vec![];
+                seen[y * width + x] = true;
+                stack.push((x, y));
+                while let Some((x, y)) = stack.pop() {
+                    group.push(Position::new(x as i32, y as i32));
+                    let neighbours = [
+                        (x.wrapping_sub(1), y),
+                        (x + 1, y),
+                        (x, y.wrapping_sub(1)),
+                        (x, y + 1),
+                    ];
+                    for &(nx, ny) in &neighbours {
+                        if nx >= width || ny >= height || seen[ny * width + nx] {
+                            continue;
+                        }
+                        if self.poyos[ny][nx].map(|p| p.color()) == Some(color) {
+                            seen[ny * width + nx] = true;
+                            stack.push((nx, ny));
                         }
                     }
                 }
+                groups.push((color, group));
+            }
+        }
+        groups
+    }
+
+    /// Same-colored neighbours the poyo at `pos` is joined to, for drawing
+    /// groups as one piece. Garbage and the falling pair are never joined.
+    pub fn links(&self, pos: Position) -> Links {
+        let mut links = Links::default();
+        let color = match self[pos] {
+            Some(p) if p.color().pops() && !self.is_current(pos) => p.color(),
+            _ => return links,
+        };
+        let neighbours = [
+            (Up, pos.up()),
+            (Right, pos.right()),
+            (Down, pos.down()),
+            (Left, pos.left()),
+        ];
+        for &(direction, neighbour) in &neighbours {
+            if self.is_inside(neighbour)
+                && !self.is_current(neighbour)
+                && self[neighbour].map(|p| p.color()) == Some(color)
+            {
+                links.join(direction);
+            }
+        }
+        links
+    }
+
+    fn is_current(&self, pos: Position) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|c| c.poyos().iter().any(|p| (p.x(), p.y()) == (pos.x, pos.y)))
+    }
+
+    /// Clears or softens the poyo at `pos`, next to a pop, counting what
+    /// it was worth in `step`.
+    fn clear_nuisance(&mut self, pos: Position, step: &mut ChainStep) {
+        match self[pos].map(|p| p.color()) {
+            Some(Color::Hard) => self[pos] = Some(Poyo::new(pos, Color::Garbage)),
+            Some(color) if color.clears() => {
+                step.push_cleared(color);
+                self[pos] = None;
             }
+            _ => {}
         }
-        removed
+    }
+
+    fn is_inside(&self, pos: Position) -> bool {
+        self.leftend() <= pos.x
+            && pos.x < self.rightend()
+            && self.top() <= pos.y
+            && pos.y < self.bottom()
     }
 }
 
@@ -279,21 +699,20 @@ mod test {
     use super::Field;
     use color::Color;
     use direction::Direction::*;
+    use piece::Piece;
     use position::Position;
     use poyo::Poyo;
     use poyopoyo::PoyoPoyo;
     use size::Size;
-    use std::io::stderr;
-    use std::io::Write;
 
     #[test]
     fn test_field_current_poyo_reflects_poyos() {
         let mut field = Field::default();
         let p1 = Poyo::default();
         let p2 = Poyo::new(Position::new(1, 0), Color::Red);
-        let pp = PoyoPoyo::new(p1.clone(), p2.clone());
+        let pp = PoyoPoyo::new(p1, p2);
         field.set_current(pp.clone());
-        assert_eq!(field.current, Some(pp.clone()));
+        assert_eq!(field.current, Some(pp.clone().into()));
         let (x1, x2) = pp.x();
         let (y1, y2) = pp.y();
         assert_eq!(field.poyos[y1 as usize][x1 as usize], Some(p1));
@@ -305,12 +724,15 @@ mod test {
         let mut field = Field::default();
         let mut p1 = Poyo::default();
         let mut p2 = Poyo::new(Position::new(1, 0), Color::Red);
-        let pp = PoyoPoyo::new(p1.clone(), p2.clone());
+        let pp = PoyoPoyo::new(p1, p2);
         let (x1, x2) = pp.x();
         let (y1, y2) = pp.y();
         field.set_current(pp);
         field.move_current(Down);
-        assert_eq!(Some((y1 + 1, y2 + 1)), field.current.map(|p| p.y()));
+        assert_eq!(
+            Some((y1 + 1, y2 + 1)),
+            field.current.map(|p| p.pair().unwrap().y())
+        );
         p1.moves(Down);
         p2.moves(Down);
         assert_eq!(field.poyos[y1 as usize][x1 as usize], None);
@@ -323,10 +745,10 @@ mod test {
         let mut field = Field::default();
         let p1 = Poyo::default();
         let p2 = Poyo::new(Position::new(1, 0), Color::Red);
-        let pp = PoyoPoyo::new(p1.clone(), p2.clone());
+        let pp = PoyoPoyo::new(p1, p2);
         field.set_current(pp);
-        assert_eq!(field.current_can_move(&Left), false);
-        assert_eq!(field.current_can_move(&Right), true);
+        assert!(!field.current_can_move(&Left));
+        assert!(field.current_can_move(&Right));
     }
 
     #[test]
@@ -334,10 +756,10 @@ mod test {
         let mut field = Field::default();
         let p1 = Poyo::new(Position::new(field.rightend() - 2, 0), Color::Red);
         let p2 = Poyo::new(Position::new(field.rightend() - 1, 0), Color::Red);
-        let pp = PoyoPoyo::new(p1.clone(), p2.clone());
+        let pp = PoyoPoyo::new(p1, p2);
         field.set_current(pp);
-        assert_eq!(field.current_can_move(&Right), false);
-        assert_eq!(field.current_can_move(&Left), true);
+        assert!(!field.current_can_move(&Right));
+        assert!(field.current_can_move(&Left));
     }
 
     #[test]
@@ -345,9 +767,9 @@ mod test {
         let mut field = Field::default();
         let p1 = Poyo::new(Position::new(0, field.bottom() - 2), Color::Red);
         let p2 = Poyo::new(Position::new(0, field.bottom() - 1), Color::Red);
-        let pp = PoyoPoyo::new(p1.clone(), p2.clone());
+        let pp = PoyoPoyo::new(p1, p2);
         field.set_current(pp.clone());
-        assert_eq!(field.current_can_move(&Down), false);
+        assert!(!field.current_can_move(&Down));
     }
 
     #[test]
@@ -363,7 +785,7 @@ mod test {
             Poyo::new(Position::new(2, 0), Color::Red),
         );
         field.set_current(pp);
-        assert_eq!(field.current_can_move(&Left), false);
+        assert!(!field.current_can_move(&Left));
     }
 
     #[test]
@@ -383,18 +805,16 @@ mod test {
             .collect();
         let size = Size::new(3, 3);
         let mut field = Field {
-            size: size,
-            poyos: poyos,
+            size,
+            poyos,
             ..Default::default()
         };
         field.transpose();
         let mut actual = vec![];
         for row in field.poyos {
             let mut nrow = vec![];
-            for poyo in row {
-                if let Some(poyo) = poyo {
-                    nrow.push((poyo.x(), poyo.y()))
-                }
+            for poyo in row.into_iter().flatten() {
+                nrow.push((poyo.x(), poyo.y()))
             }
             actual.push(nrow);
         }
@@ -423,8 +843,8 @@ mod test {
             .collect();
         let size = Size::new(3, 3);
         let mut field = Field {
-            size: size,
-            poyos: poyos,
+            size,
+            poyos,
             ..Default::default()
         };
         field.right_align();
@@ -461,8 +881,8 @@ mod test {
             .collect();
         let size = Size::new(3, 3);
         let mut field = Field {
-            size: size,
-            poyos: poyos,
+            size,
+            poyos,
             ..Default::default()
         };
         field.fall_poyos();
@@ -495,8 +915,8 @@ mod test {
             .collect();
         let size = Size::new(3, 3);
         let mut field = Field {
-            size: size,
-            poyos: poyos,
+            size,
+            poyos,
             ..Default::default()
         };
         field.fall_poyos();
@@ -515,11 +935,209 @@ mod test {
         let mut field = Field::default();
         let p1 = Poyo::new(Position::new(3, field.bottom() - 2), Color::Red);
         let p2 = Poyo::new(Position::new(3, field.bottom() - 1), Color::Red);
-        let pp = PoyoPoyo::new(p1.clone(), p2.clone());
+        let pp = PoyoPoyo::new(p1, p2);
         field.set_current(pp);
         field.fix_current();
         let current = field.current.unwrap();
+        let current = current.pair().unwrap();
         assert_eq!(current.x(), (1, 1));
         assert_eq!(current.y(), (0, 1));
     }
+
+    fn pair(x: i32, c1: Color, c2: Color) -> PoyoPoyo {
+        PoyoPoyo::new(
+            Poyo::new(Position::new(x, 0), c1),
+            Poyo::new(Position::new(x, 1), c2),
+        )
+    }
+
+    #[test]
+    fn test_drop_pair_fires_chain() {
+        let mut field = Field::default();
+        assert_eq!(
+            field
+                .drop_pair(&pair(0, Color::Red, Color::Red))
+                .map(|c| c.len()),
+            Some(0)
+        );
+        assert!(!field.is_empty());
+        let chain = field.drop_pair(&pair(0, Color::Red, Color::Red)).unwrap();
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain.removed(), 4);
+        assert_eq!(field.column_height(0), 0);
+        assert!(field.is_empty());
+        assert!(chain.is_all_clear());
+        field.spawn();
+        assert!(field.is_empty());
+    }
+
+    #[test]
+    fn test_drop_pair_into_full_column() {
+        let mut field = Field::default();
+        for i in 0..6 {
+            let color = if i % 2 == 0 { Color::Red } else { Color::Blue };
+            assert!(field.drop_pair(&pair(0, color, color)).is_some());
+        }
+        assert_eq!(field.column_height(0), 12);
+        assert_eq!(field.drop_pair(&pair(0, Color::Red, Color::Red)), None);
+    }
+
+    #[test]
+    fn test_pop_clears_adjacent_garbage() {
+        let mut field = Field::default();
+        field.drop_garbage(6);
+        for x in 0..6 {
+            assert_eq!(field.column_height(x), 1);
+        }
+        field.drop_pair(&pair(0, Color::Red, Color::Red));
+        let chain = field.drop_pair(&pair(1, Color::Red, Color::Red)).unwrap();
+        assert_eq!(chain.removed(), 4);
+        assert_eq!(field.column_height(0), 0);
+        assert_eq!(field.column_height(1), 0);
+        assert_eq!(field.column_height(2), 1);
+        assert!(!chain.is_all_clear());
+    }
+
+    #[test]
+    fn test_spawn_blocked() {
+        let mut field = Field::default();
+        for i in 0..6 {
+            let color = if i % 2 == 0 { Color::Red } else { Color::Blue };
+            field.drop_pair(&pair(1, color, color));
+        }
+        assert!(!field.spawn());
+        assert_eq!(field.current(), None);
+    }
+
+    #[test]
+    fn test_scripted_pairs_run_out() {
+        let mut field = Field::default();
+        field.set_pairs(vec![
+            PoyoPoyo::spawned(Color::Red, Color::Blue),
+            PoyoPoyo::spawned(Color::Green, Color::Green),
+        ]);
+        assert!(field.spawn());
+        assert_eq!(field.current().unwrap().poyos()[1].color(), Color::Blue);
+        assert_eq!(field.next().len(), 1);
+        field.lock_current();
+        assert!(field.spawn());
+        field.lock_current();
+        assert!(!field.spawn());
+    }
+
+    #[test]
+    fn test_text_form() {
+        let lines = ["......", "..R...", "OGRB.Y"];
+        let field = Field::from_lines(&lines).unwrap();
+        assert_eq!(field.width(), 6);
+        assert_eq!(field.height(), 3);
+        assert_eq!(field.to_lines(), lines);
+        assert!(Field::from_lines(&["...", ".."]).is_err());
+        assert!(Field::from_lines(&["..X"]).is_err());
+    }
+
+    #[test]
+    fn test_same_seed_deals_same_pairs() {
+        let mut a = Field::with_seed(Size::new(6, 12), 42);
+        let mut b = Field::with_seed(Size::new(6, 12), 42);
+        for _ in 0..10 {
+            a.spawn();
+            b.spawn();
+            assert_eq!(a.current(), b.current());
+            a.lock_current();
+            b.lock_current();
+        }
+    }
+
+    #[test]
+    fn test_fewer_colors() {
+        for seed in 0..50 {
+            let field = Field::with_colors(Size::new(6, 12), seed, 3);
+            assert_eq!(field.colors(), 3);
+            for piece in field.next() {
+                assert!(piece.poyos().iter().all(|p| p.color() != Color::Blue));
+            }
+        }
+    }
+
+    #[test]
+    fn test_groups() {
+        let field = Field::from_lines(&["RRO...", ".RGG..", "BBOG.."]).unwrap();
+        let groups = field.groups();
+        let sizes: Vec<(Color, usize)> = groups.iter().map(|g| (g.0, g.1.len())).collect();
+        assert_eq!(
+            sizes,
+            vec![(Color::Red, 3), (Color::Green, 3), (Color::Blue, 2)]
+        );
+    }
+
+    #[test]
+    fn test_special_poyos() {
+        let lines = ["..R...", "..W...", "H.....", "RP....", "RRRS.."];
+        let mut field = Field::from_lines(&lines).unwrap();
+        let chain = field.resolve();
+        assert_eq!(chain.len(), 1);
+        // The point poyo scores on top of the four reds.
+        assert_eq!(chain.score(), 40 + 100);
+        assert_eq!(chain.sun_garbage(), 3);
+        // The hard garbage softened, and the red stays on the block.
+        let expected = ["..R...", "..W...", "......", "......", "O....."];
+        assert_eq!(field.to_lines(), expected);
+        assert!(!field.drop_poyo(Poyo::new(Position::new(2, 0), Color::Red)));
+        assert!(field.drop_poyo(Poyo::new(Position::new(1, 0), Color::Red)));
+        assert_eq!(field.to_lines()[4], "OR....");
+    }
+
+    #[test]
+    fn test_boxed_pieces() {
+        let lines = ["....", "...G", "....", "....", "...."];
+        let mut field = Field::from_lines(&lines).unwrap();
+        field.set_current(Piece::triple(Color::Red, Color::Blue));
+        assert!(!field.current_can_move(&Right));
+        field.rotate_current();
+        field.move_current(Right);
+        assert_eq!(field.current().unwrap().left(), 2);
+        // Turning into the green kicks the box a column left.
+        field.rotate_current();
+        assert_eq!(field.current().unwrap().left(), 1);
+        assert_eq!(field.to_lines()[..2], [".BR.", "..RG"]);
+        let landing = field.landing().unwrap();
+        let cells: Vec<_> = landing.iter().map(|p| (p.x(), p.y())).collect();
+        assert_eq!(cells, vec![(1, 4), (2, 3), (2, 4)]);
+        field.lock_current();
+        assert_eq!(field.to_lines()[3..], ["..R.", ".BRG"]);
+    }
+
+    #[test]
+    fn test_landing() {
+        let lines = ["....", "....", "....", "G...", "GR.."];
+        let mut field = Field::from_lines(&lines).unwrap();
+        field.set_current(PoyoPoyo::new(
+            Poyo::new(Position::new(0, 0), Color::Blue),
+            Poyo::new(Position::new(1, 0), Color::Yellow),
+        ));
+        // A horizontal pair splits over the uneven stack.
+        let landing = field.landing().unwrap();
+        assert_eq!((landing[0].x(), landing[0].y()), (0, 2));
+        assert_eq!((landing[1].x(), landing[1].y()), (1, 3));
+        let mut field = Field::from_lines(&lines).unwrap();
+        field.set_current(PoyoPoyo::new(
+            Poyo::new(Position::new(2, 0), Color::Blue),
+            Poyo::new(Position::new(2, 1), Color::Yellow),
+        ));
+        let landing = field.landing().unwrap();
+        assert_eq!((landing[0].y(), landing[1].y()), (3, 4));
+        assert_eq!(landing[1].color(), Color::Yellow);
+    }
+
+    #[test]
+    fn test_links() {
+        let field = Field::from_lines(&["RRO...", ".RGG..", "BBOG.."]).unwrap();
+        let links = field.links(Position::new(1, 0));
+        assert!(links.has(Left) && links.has(Down));
+        assert!(!links.has(Right) && !links.has(Up));
+        assert_eq!(field.links(Position::new(2, 1)).index(), 2);
+        assert_eq!(field.links(Position::new(2, 0)).index(), 0);
+        assert_eq!(field.links(Position::new(4, 0)).index(), 0);
+    }
 }
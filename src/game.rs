@@ -0,0 +1,194 @@
+use action::Action;
+use chain::Chain;
+use mission::Rain;
+use player::Player;
+use puzzle::Objective;
+use race::{Race, Split};
+
+/// An action applied on a given frame, enough to replay a match.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub frame: u64,
+    pub player: usize,
+    pub action: Action,
+}
+
+/// The players of one match. With two players, chains are sent to the
+/// opponent as garbage.
+pub struct Game {
+    players: Vec<Player>,
+    frame: u64,
+    records: Vec<Record>,
+    objective: Option<Objective>,
+    race: Option<Race>,
+    splits: Vec<Split>,
+    rain: Option<Rain>,
+}
+
+impl Game {
+    pub fn new(players: Vec<Player>) -> Self {
+        Game {
+            players,
+            frame: 0,
+            records: vec![],
+            objective: None,
+            race: None,
+            splits: vec![],
+            rain: None,
+        }
+    }
+
+    /// Ends the game as soon as the first player meets `objective`.
+    pub fn with_objective(mut self, objective: Objective) -> Self {
+        self.objective = Some(objective);
+        self
+    }
+
+    /// Has every player play by fever rules.
+    pub fn with_fever(mut self) -> Self {
+        for player in &mut self.players {
+            player.set_fever();
+        }
+        self
+    }
+
+    /// Has every player practice, with undo and no gravity.
+    pub fn with_practice(mut self) -> Self {
+        for player in &mut self.players {
+            player.set_practice();
+        }
+        self
+    }
+
+    /// Plays against the clock; a sprint ends once its objective is met.
+    pub fn with_race(mut self, race: Race) -> Self {
+        self.objective = race.objective().or(self.objective);
+        self.race = Some(race);
+        self
+    }
+
+    /// Has garbage fall on every player every so often.
+    pub fn with_rain(mut self, rain: Rain) -> Self {
+        self.rain = Some(rain);
+        self
+    }
+
+    pub fn race(&self) -> Option<Race> {
+        self.race
+    }
+
+    /// Splits of the race so far.
+    pub fn splits(&self) -> &[Split] {
+        &self.splits
+    }
+
+    pub fn objective(&self) -> Option<Objective> {
+        self.objective
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn players_mut(&mut self) -> &mut [Player] {
+        &mut self.players
+    }
+
+    /// Frames played so far.
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Every action applied so far, in order.
+    pub fn records(&self) -> &[Record] {
+        &self.records
+    }
+
+    pub fn on_action(&mut self, player: usize, action: Action) {
+        self.records.push(Record {
+            frame: self.frame,
+            player,
+            action,
+        });
+        if let Some(chain) = self.players[player].on_action(action) {
+            self.on_chain(player, &chain);
+        }
+    }
+
+    pub fn on_frame(&mut self) {
+        for i in 0..self.players.len() {
+            while let Some(action) = self.players[i].plan() {
+                self.on_action(i, action);
+            }
+            if let Some(chain) = self.players[i].on_frame() {
+                self.on_chain(i, &chain);
+            }
+        }
+        self.frame += 1;
+        if let Some(race) = self.race {
+            if race.is_split(self.splits.len(), self.frame, &self.players[0]) {
+                self.splits.push(Split {
+                    frame: self.frame,
+                    score: self.players[0].score(),
+                });
+            }
+        }
+        if let Some(rain) = self.rain {
+            if rain.is_due(self.frame) {
+                for player in &mut self.players {
+                    player.receive(rain.amount);
+                }
+            }
+        }
+    }
+
+    fn on_chain(&mut self, from: usize, chain: &Chain) {
+        // Alone, a player only attacks to offset rain or to send garbage
+        // for the objective.
+        let alone = self.players.len() < 2
+            && self.rain.is_none()
+            && !matches!(self.objective, Some(Objective::Garbage(_)));
+        if alone || chain.is_empty() {
+            return;
+        }
+        let garbage = self.players[from].attack(chain);
+        for (i, p) in self.players.iter_mut().enumerate() {
+            if i != from {
+                p.receive(garbage);
+            }
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.players.iter().any(|p| p.is_dead())
+            || self.is_cleared() == Some(true)
+            || self.race.is_some_and(|r| r.is_up(self.frame))
+    }
+
+    /// Whether the objective is met, for games that have one. Surviving
+    /// is met once the time is up with the first player still alive.
+    pub fn is_cleared(&self) -> Option<bool> {
+        let player = &self.players[0];
+        self.objective.map(|o| match o {
+            Objective::Survive => {
+                self.race.is_some_and(|r| r.is_up(self.frame)) && !player.is_dead()
+            }
+            _ => o.is_met(player),
+        })
+    }
+
+    /// The last player standing, once the others are dead.
+    pub fn winner(&self) -> Option<usize> {
+        if self.players.len() < 2 || !self.is_over() {
+            return None;
+        }
+        let alive: Vec<usize> = (0..self.players.len())
+            .filter(|&i| !self.players[i].is_dead())
+            .collect();
+        if alive.len() == 1 {
+            Some(alive[0])
+        } else {
+            None
+        }
+    }
+}
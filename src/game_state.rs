@@ -1,6 +1,7 @@
-use field::PoyoRows;
 pub enum GameState {
     Start,
-    Playing { poyos: PoyoRows },
+    /// Frames left before a game against the clock starts.
+    Countdown(u32),
+    Playing,
     GameOver,
 }
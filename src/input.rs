@@ -3,23 +3,29 @@ use ncurses::*;
 use std::sync::mpsc::Sender;
 use std::thread;
 
+/// How long `getch` blocks, in milliseconds. Waking up regularly lets
+/// ncurses report a terminal resize without waiting for a key press.
+const POLL: i32 = 100;
+
 pub struct Input {
     tx: Sender<Event>,
 }
 
 impl Input {
     pub fn new(tx: Sender<Event>) -> Self {
-        Input { tx: tx }
+        Input { tx }
     }
 
+    /// Sends every key to the main loop, which decides when `q` quits.
     pub fn run(self) {
         let _ = thread::spawn(move || {
-            let mut ch = getch();
-            while ch != 0x71 {
-                let _ = self.tx.send(Event::Input(ch));
-                ch = getch();
+            timeout(POLL);
+            loop {
+                let ch = getch();
+                if ch != ERR && self.tx.send(Event::Input(ch)).is_err() {
+                    break;
+                }
             }
-            let _ = self.tx.send(Event::Exit);
         });
     }
 }
@@ -0,0 +1,171 @@
+use size::Size;
+
+/// Width of the panels next to each well, borders included.
+const PANEL_WIDTH: i32 = 11;
+/// Width of the stats panel, shown outside the others when there is room.
+const STATS_WIDTH: i32 = 16;
+/// Columns between the blocks of two players.
+const GAP: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Where one player's well and panels go.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub well: Rect,
+    pub next: Rect,
+    pub score: Rect,
+    pub level: Rect,
+    pub chain: Rect,
+    pub garbage: Rect,
+    pub stats: Option<Rect>,
+}
+
+/// Screen positions of every window, recomputed whenever the terminal is
+/// resized. The wells are centered with the panels beside them, on the
+/// outer side in versus, and cells are two columns wide when there is room.
+/// Stats go on the outer side of the panels if there is still room.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub cell_width: i32,
+    pub blocks: Vec<Block>,
+}
+
+impl Layout {
+    /// Lays `players` wells of `field` out on `screen`, or `None` if they
+    /// do not fit even with narrow cells.
+    pub fn new(screen: &Size, field: &Size, players: usize) -> Option<Layout> {
+        let (width, height) = (screen.width as i32, screen.height as i32);
+        let cell_width = if Layout::width(field, players, 2) <= width {
+            2
+        } else {
+            1
+        };
+        let mut total = Layout::width(field, players, cell_width);
+        let block_height = Layout::block_height(field);
+        if total > width || block_height > height {
+            return None;
+        }
+        let stats_width = if total + STATS_WIDTH * players as i32 <= width {
+            STATS_WIDTH
+        } else {
+            0
+        };
+        total += stats_width * players as i32;
+        let well_width = field.width as i32 * cell_width + 2;
+        let mut x = (width - total) / 2;
+        let y = (height - block_height) / 2;
+        let mut blocks = vec![];
+        for i in 0..players {
+            let panel_left = players == 2 && i == 0;
+            let (well_x, panel_x, stats_x) = if panel_left {
+                (x + stats_width + PANEL_WIDTH, x + stats_width, x)
+            } else {
+                (x, x + well_width, x + well_width + PANEL_WIDTH)
+            };
+            let well = Rect::new(well_x, y, well_width, field.height as i32 + 2);
+            let next = Rect::new(panel_x, y, PANEL_WIDTH, 4);
+            let score = Rect::new(panel_x, next.y + next.height, PANEL_WIDTH, 3);
+            let level = Rect::new(panel_x, score.y + score.height, PANEL_WIDTH, 3);
+            let chain = Rect::new(panel_x, level.y + level.height, PANEL_WIDTH, 3);
+            let garbage = Rect::new(panel_x, chain.y + chain.height, PANEL_WIDTH, 3);
+            let stats = if stats_width > 0 {
+                Some(Rect::new(stats_x, y, stats_width, block_height))
+            } else {
+                None
+            };
+            blocks.push(Block {
+                well,
+                next,
+                score,
+                level,
+                chain,
+                garbage,
+                stats,
+            });
+            x += well_width + PANEL_WIDTH + stats_width + GAP;
+        }
+        Some(Layout { cell_width, blocks })
+    }
+
+    /// The smallest screen `players` wells of `field` fit on.
+    pub fn minimum(field: &Size, players: usize) -> Size {
+        Size::new(
+            Layout::width(field, players, 1) as usize,
+            Layout::block_height(field) as usize,
+        )
+    }
+
+    fn width(field: &Size, players: usize, cell_width: i32) -> i32 {
+        let block = field.width as i32 * cell_width + 2 + PANEL_WIDTH;
+        block * players as i32 + GAP * (players as i32 - 1)
+    }
+
+    fn block_height(field: &Size) -> i32 {
+        (field.height as i32 + 2).max(4 + 3 * 4)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Layout, Rect};
+    use size::Size;
+
+    #[test]
+    fn test_centered_with_wide_cells() {
+        let layout = Layout::new(&Size::new(80, 24), &Size::new(6, 12), 1).unwrap();
+        assert_eq!(layout.cell_width, 2);
+        let block = &layout.blocks[0];
+        // 14 columns of well, 11 of panel and 16 of stats, centered on 80.
+        assert_eq!(block.well, Rect::new(19, 4, 14, 14));
+        assert_eq!(block.next, Rect::new(33, 4, 11, 4));
+        assert_eq!(block.garbage.y + block.garbage.height, 20);
+        assert_eq!(block.stats, Some(Rect::new(44, 4, 16, 16)));
+    }
+
+    #[test]
+    fn test_versus_panels_outside() {
+        let layout = Layout::new(&Size::new(80, 24), &Size::new(6, 12), 2).unwrap();
+        assert_eq!(layout.cell_width, 2);
+        let (a, b) = (&layout.blocks[0], &layout.blocks[1]);
+        assert!(a.next.x < a.well.x);
+        assert!(b.well.x < b.next.x);
+        assert!(a.well.x + a.well.width < b.well.x);
+        assert_eq!(a.stats, None);
+        let layout = Layout::new(&Size::new(120, 24), &Size::new(6, 12), 2).unwrap();
+        let (a, b) = (&layout.blocks[0], &layout.blocks[1]);
+        assert!(a.stats.unwrap().x < a.next.x);
+        assert!(b.next.x < b.stats.unwrap().x);
+    }
+
+    #[test]
+    fn test_narrow_cells_then_too_small() {
+        let field = Size::new(6, 12);
+        assert_eq!(
+            Layout::new(&Size::new(50, 24), &field, 2)
+                .unwrap()
+                .cell_width,
+            1
+        );
+        assert_eq!(Layout::minimum(&field, 2), Size::new(40, 16));
+        assert!(Layout::new(&Size::new(39, 24), &field, 2).is_none());
+        assert!(Layout::new(&Size::new(80, 15), &field, 2).is_none());
+    }
+}
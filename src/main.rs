@@ -1,70 +1,237 @@
+extern crate libc;
 extern crate ncurses;
 extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 
+mod action;
+mod ai;
+mod ansi;
+mod bench;
+mod bitboard;
+mod bot;
+mod chain;
 mod cli;
 mod color;
 mod direction;
+mod editor;
 mod event;
+mod fever;
 mod field;
+mod game;
 mod game_state;
 mod input;
+mod layout;
+mod menu;
+mod mission;
+mod options;
+mod paths;
+mod piece;
+mod player;
 mod position;
+mod potential;
 mod poyo;
 mod poyopoyo;
+mod practice;
+mod puzzle;
+mod race;
+mod replay;
+mod scores;
+mod screen;
+mod settings;
+mod shift;
 mod size;
+mod stats;
+mod theme;
+mod tournament;
 
-use direction::Direction;
+use action::{key_name, Action, QUIT};
+use ai::{Ai, Level};
+use bot::Bot;
+use editor::{Editor, Request};
 use event::Event;
 use field::Field;
+use game::Game;
 use game_state::GameState;
 use input::Input;
-use poyopoyo::PoyoPoyo;
+use menu::{Choice, Nav};
+use mission::{Campaign, Mission};
+use ncurses::{KEY_BACKSPACE, KEY_ENTER, KEY_RESIZE};
+use options::{Mode, Options};
+use player::{Controller, Player};
+use practice::Command;
+use puzzle::{Objective, Puzzle};
+use race::{Race, ATTACK, COUNTDOWN, SPRINT_GOAL};
+use replay::Replay;
+use scores::{Entry, Rules, Scores, NAME_LENGTH};
+use screen::{Page, Screen};
+use settings::Settings;
+use shift::AutoShift;
 use size::Size;
+use stats::{Report, Seat};
+use std::env;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::mpsc::{channel, Receiver};
 use std::thread;
-use std::time::SystemTime;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tournament::{Entrant, Tournament, MAX_FRAMES};
 
 struct Main {
-    state: GameState,
+    screen: Screen,
     done: bool,
-    field: Field,
+    game: Game,
+    /// Seed and seat names of the game, to save it as a replay when it ends.
+    record: Option<(u32, Vec<String>)>,
+    /// Seed and seat names of the game, to write its stats when it ends.
+    seats: Option<(u32, Vec<String>)>,
+    /// Rules the game is ranked under, if its first player is human.
+    rules: Option<Rules>,
+    /// The game just over, by the rules it ranks under.
+    pending: Option<(String, Entry)>,
+    scores: Scores,
+    /// Name new records are entered under unless another is typed.
+    name: String,
+    /// The settings in effect, with the command line's on top of the file's.
+    settings: Settings,
+    /// The settings as saved, which the Settings menu edits.
+    file: Settings,
+    /// Auto-shift of each human, in the order of the key settings.
+    shifts: Vec<AutoShift>,
+    /// Section and name of the key setting waiting for a key press.
+    binding: Option<(&'static str, &'static str)>,
+    puzzles: Vec<Puzzle>,
+    /// The editor a game was played from, to go back to once it is over.
+    editor: Option<Box<Editor>>,
+    missions: Vec<Mission>,
+    campaign: Campaign,
+    /// The mission being played, by index.
+    mission: Option<usize>,
     view: cli::CliView,
     rx: Receiver<Event>,
 }
 
 fn main() {
+    let file = match Settings::load(&Settings::path()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let options = match Options::parse_over(file.options(), env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n{}", e, options::USAGE);
+            process::exit(2);
+        }
+    };
+    let settings = match file.clone().with_options(&options) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    if options.mode == Mode::Bench {
+        bench::run(options.iterations, options.seed.unwrap_or(0));
+        return;
+    }
+    if options.mode == Mode::Tournament || options.headless {
+        let played = if options.headless {
+            headless(&options, &settings)
+        } else {
+            tournament(&options, &settings)
+        };
+        if let Err(e) = played {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    let scores = match Scores::load(&Scores::path()) {
+        Ok(scores) => scores,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let campaign = match Campaign::load(&Campaign::path()) {
+        Ok(campaign) => campaign,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
     let (tx, rx) = channel();
-    let mut field = Field::new(tx.clone(), Size::new(6, 12));
-    let pp = PoyoPoyo::rand();
-    field.set_current(pp);
-    let cv = cli::CliView::new(Size::new(field.width() + 2, field.height() + 2));
+    let theme = match settings.theme() {
+        Ok(theme) => theme,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let mut m = if let Some(ref path) = options.replay {
+        let replay = match Replay::load(Path::new(path)) {
+            Ok(replay) => replay,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+        let size = Size::new(replay.width, replay.height);
+        let cv = cli::CliView::new(size, replay.players.len(), theme);
+        let screen = Screen::Game(GameState::Start);
+        Main::new(cv, screen, replay.game(), settings, file, scores, rx)
+    } else if options.mode == Mode::Menu {
+        let cv = cli::CliView::new(settings.size(), 0, theme);
+        Main::new(
+            cv,
+            screen::title(),
+            Game::new(vec![]),
+            settings,
+            file,
+            scores,
+            rx,
+        )
+    } else {
+        let controllers = match controllers(&options) {
+            Ok(controllers) => controllers,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+        let cv = cli::CliView::new(settings.size(), controllers.len(), theme);
+        let seed = options.seed.unwrap_or_else(rand::random);
+        let game = new_game(options.mode, controllers, &settings, seed, 1, options.goal);
+        let screen = Screen::Game(GameState::Start);
+        let mut m = Main::new(cv, screen, game, settings, file, scores, rx);
+        m.seats = Some((seed, seat_names(&m.game)));
+        m.track(options.mode, seed);
+        m
+    };
+    m.campaign = campaign;
     let input = Input::new(tx.clone());
-    let mut m = Main::new(cv, field, rx);
     m.on_init();
     {
         let tx = tx.clone();
         let mut time = SystemTime::now();
         let mut frame = 0;
-        let mut tick = 0;
         thread::spawn(move || loop {
             let _time = SystemTime::now();
-            match _time.duration_since(time) {
-                Ok(diff) => {
-                    frame += diff.subsec_nanos();
-                    tick += diff.subsec_nanos();
-                    time = _time
-                }
-                Err(_) => {}
+            if let Ok(diff) = _time.duration_since(time) {
+                frame += diff.subsec_nanos();
+                time = _time
             };
-            // if frame > 150 * 1000000 {
             if frame > 10 * 1000000 {
                 let _ = tx.send(Event::FrameUpdate);
                 frame = 0;
             }
-            if tick > 1000 * 1000000 {
-                let _ = tx.send(Event::Tick);
-                tick = 0;
-            }
         });
     }
     input.run();
@@ -72,72 +239,864 @@ fn main() {
     m.on_exit();
 }
 
+/// Seats of the chosen mode. Bots take the last seats, in the order given.
+fn controllers(options: &Options) -> Result<Vec<Controller>, String> {
+    let mut controllers = match options.mode {
+        Mode::Endless | Mode::TimeAttack | Mode::Sprint | Mode::Practice => {
+            vec![Controller::Human]
+        }
+        Mode::Versus | Mode::Fever => {
+            vec![Controller::Human, Controller::Cpu(Ai::new(options.level))]
+        }
+        Mode::WatchAi => vec![Controller::Cpu(Ai::new(options.level))],
+        Mode::Menu | Mode::Tournament | Mode::Bench => unreachable!("no game to set up"),
+    };
+    if options.bots.len() > controllers.len() {
+        return Err(format!(
+            "{} bots given but this mode has {} seat(s)",
+            options.bots.len(),
+            controllers.len()
+        ));
+    }
+    let first = controllers.len() - options.bots.len();
+    for (i, command) in options.bots.iter().enumerate() {
+        controllers[first + i] = Controller::Bot(Bot::spawn(command)?);
+    }
+    Ok(controllers)
+}
+
+/// A game of `mode` dealt from `seed` by the rules of `settings`, every
+/// player starting at `level`. A sprint is run for `goal`, or else for
+/// `SPRINT_GOAL`.
+fn new_game(
+    mode: Mode,
+    controllers: Vec<Controller>,
+    settings: &Settings,
+    seed: u32,
+    level: usize,
+    goal: Option<Objective>,
+) -> Game {
+    let players = controllers
+        .into_iter()
+        .map(|c| {
+            let field = Field::with_colors(settings.size(), seed, settings.colors);
+            let mut player = Player::new(field, c);
+            player.set_start_level(level);
+            player.set_curve(settings.curve);
+            player
+        })
+        .collect();
+    let game = Game::new(players);
+    match mode {
+        Mode::Fever => game.with_fever(),
+        Mode::TimeAttack => game.with_race(Race::Attack(ATTACK)),
+        Mode::Sprint => game.with_race(Race::Sprint(goal.unwrap_or(SPRINT_GOAL))),
+        Mode::Practice => game.with_practice(),
+        _ => game,
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Writes `lines` of the field text form to a new file of the fields
+/// directory.
+fn save_field(lines: &[String]) -> Result<PathBuf, String> {
+    let dir = paths::field_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.txt", now()));
+    fs::write(&path, lines.join("\n") + "\n")
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+fn new_shifts(settings: &Settings) -> Vec<AutoShift> {
+    settings
+        .keys
+        .iter()
+        .map(|_| AutoShift::new(settings.das, settings.arr))
+        .collect()
+}
+
+/// Names of the saved replays, newest first.
+fn replay_names() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(paths::replay_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .filter(|name| name.ends_with(".json"))
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort_by(|a, b| b.cmp(a));
+    names
+}
+
+/// Name of a seat in replays and reports.
+fn seat_name(controller: &Controller) -> String {
+    match *controller {
+        Controller::Human => "human".to_string(),
+        Controller::Cpu(ref ai) => format!("cpu:{:?}", ai.level()).to_lowercase(),
+        Controller::Bot(ref bot) => bot.command().to_string(),
+        Controller::Replay(_) => "replay".to_string(),
+    }
+}
+
+fn seat_names(game: &Game) -> Vec<String> {
+    game.players()
+        .iter()
+        .map(|p| seat_name(p.controller()))
+        .collect()
+}
+
+/// Writes the stats of every player of `game` to a new JSON report in
+/// `dir`, returning its path.
+fn save_stats(game: &Game, seed: u32, names: &[String], dir: &Path) -> Result<PathBuf, String> {
+    let frames = game.frame();
+    let players = game
+        .players()
+        .iter()
+        .zip(names)
+        .map(|(p, name)| Seat {
+            name: name.clone(),
+            score: p.score(),
+            pieces_per_minute: p.stats().per_minute(frames),
+            stats: p.stats().clone(),
+        })
+        .collect();
+    let report = Report {
+        seed,
+        frames,
+        date: now(),
+        players,
+    };
+    let path = dir.join(format!("{}-{}.json", report.date, seed));
+    report.save(&path).map(|_| path)
+}
+
+/// Plays the game the options set up, or their replay, to the end with no
+/// view, then prints every field and how the game went.
+fn headless(options: &Options, settings: &Settings) -> Result<(), String> {
+    let (game, names) = match options.replay {
+        Some(ref path) => {
+            let replay = Replay::load(Path::new(path))?;
+            (replay.run(), replay.players)
+        }
+        None => {
+            let mut controllers = match options.mode {
+                Mode::Menu => vec![Controller::Human],
+                _ => controllers(options)?,
+            };
+            if controllers.iter().any(|c| matches!(*c, Controller::Human)) {
+                return Err(
+                    "--headless needs the CPU or a bot in every seat: add --mode watch-ai, \
+                     --bot CMD or --replay FILE"
+                        .to_string(),
+                );
+            }
+            for controller in &mut controllers {
+                if let Controller::Bot(ref mut bot) = *controller {
+                    bot.set_blocking(true);
+                }
+            }
+            let names = controllers.iter().map(seat_name).collect();
+            let seed = options.seed.unwrap_or_else(rand::random);
+            println!("seed {}", seed);
+            let mut game = new_game(options.mode, controllers, settings, seed, 1, options.goal);
+            while !game.is_over() && game.frame() < MAX_FRAMES {
+                game.on_frame();
+                for player in game.players() {
+                    if let Controller::Bot(ref bot) = *player.controller() {
+                        if let Some(e) = bot.error() {
+                            return Err(format!("{}: {}", bot.command(), e));
+                        }
+                    }
+                }
+            }
+            (game, names)
+        }
+    };
+    let theme = settings.theme()?;
+    for (i, (player, name)) in game.players().iter().zip(names.iter()).enumerate() {
+        println!(
+            "player {} ({}): score {}, max chain {}",
+            i + 1,
+            name,
+            player.score(),
+            player.max_chain()
+        );
+        for line in ansi::render(player.field(), &theme, player.garbage()) {
+            println!("{}", line);
+        }
+    }
+    let winner = match game.winner() {
+        _ if !game.is_over() => ", stopped at the time limit".to_string(),
+        Some(w) if game.players().len() > 1 => format!(", winner player {}", w + 1),
+        _ => String::new(),
+    };
+    println!("{} frames{}", game.frame(), winner);
+    Ok(())
+}
+
+fn tournament(options: &Options, settings: &Settings) -> Result<(), String> {
+    let mut entrants = [Entrant::Cpu(options.level), Entrant::Cpu(options.level)];
+    for (i, command) in options.bots.iter().enumerate() {
+        entrants[i] = Entrant::Bot(command.clone());
+    }
+    if let Some(ref dir) = options.replays {
+        fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir, e))?;
+    }
+    let seed = options.seed.unwrap_or_else(rand::random);
+    let tournament = Tournament::new(entrants, seed, settings.size());
+    let report = tournament.run(options.games, |n, result| {
+        let winner = match result.winner {
+            Some(w) => tournament.entrant(w).name(),
+            None => "draw".to_string(),
+        };
+        println!(
+            "match {}: seed {}, {} frames, winner {}, scores {}/{}, max chains {}/{}",
+            n + 1,
+            result.seed,
+            result.replay.frames,
+            winner,
+            result.scores[0],
+            result.scores[1],
+            result.max_chains[0],
+            result.max_chains[1]
+        );
+        match options.replays {
+            Some(ref dir) => result
+                .replay
+                .save(&Path::new(dir).join(format!("match-{:04}.json", n + 1))),
+            None => Ok(()),
+        }
+    })?;
+    print!("{}", report);
+    Ok(())
+}
+
 impl Main {
-    fn new(cv: cli::CliView, field: Field, rx: Receiver<Event>) -> Self {
+    fn new(
+        cv: cli::CliView,
+        screen: Screen,
+        game: Game,
+        settings: Settings,
+        file: Settings,
+        scores: Scores,
+        rx: Receiver<Event>,
+    ) -> Self {
+        let shifts = new_shifts(&settings);
         Main {
-            state: GameState::Start,
+            screen,
             done: false,
             view: cv,
-            field: field,
-            rx: rx,
+            game,
+            record: None,
+            seats: None,
+            rules: None,
+            pending: None,
+            scores,
+            name: env::var("USER").unwrap_or_else(|_| "player".to_string()),
+            settings,
+            file,
+            shifts,
+            binding: None,
+            puzzles: Puzzle::builtin()
+                .into_iter()
+                .chain(Puzzle::saved(&paths::puzzle_dir()))
+                .collect(),
+            editor: None,
+            missions: Mission::builtin(),
+            campaign: Campaign::default(),
+            mission: None,
+            rx,
         }
     }
     fn on_init(&mut self) {
         self.view.init();
-        self.field.on_init();
     }
 
     fn on_frame(&mut self) {
-        // self.view.update();
-        self.view.draw(&self.state);
+        if let Screen::Game(GameState::Countdown(ref mut left)) = self.screen {
+            *left -= 1;
+            if *left == 0 {
+                self.screen = Screen::Game(GameState::Playing);
+            }
+        }
+        if let Screen::Game(GameState::Playing) = self.screen {
+            self.shift();
+            self.game.on_frame();
+            if self.game.is_over() {
+                self.on_game_over();
+            }
+        }
+        self.view.draw(&self.screen, &self.game);
+    }
+
+    fn on_game_over(&mut self) {
+        self.screen = Screen::Game(GameState::GameOver);
+        let seed = self.record.as_ref().map(|r| r.0).unwrap_or(0);
+        self.save_stats();
+        self.save_replay();
+        self.save_campaign();
+        let player = &self.game.players()[0];
+        let entry = Entry {
+            name: self.name.clone(),
+            score: player.score(),
+            max_chain: player.max_chain(),
+            frames: self.game.frame(),
+            date: now(),
+            seed,
+        };
+        // An unfinished sprint has no time to rank.
+        let finished = self.game.is_cleared() != Some(false);
+        self.pending = self
+            .rules
+            .take()
+            .filter(|_| finished)
+            .map(|rules| (rules.to_string(), entry));
     }
 
     fn on_exit(&self) {
         self.view.exit();
+        for player in self.game.players() {
+            if let Controller::Bot(ref bot) = *player.controller() {
+                if let Some(e) = bot.error() {
+                    eprintln!("{}: {}", bot.command(), e);
+                }
+            }
+        }
     }
 
-    fn tick(&mut self) {
-        if self.field.current_can_move(&Direction::Down) {
-            self.field.move_current(Direction::Down);
-        } else {
-            if let Some((removed_count, (left, top))) = self.field.fix_current() {
-                if removed_count == 0 && left == 1 && top == 0 {
-                    self.state = GameState::GameOver;
+    fn on_input(&mut self, key: i32) {
+        if let Some((section, name)) = self.binding.take() {
+            return self.bind(section, name, key);
+        }
+        let choice = match self.screen {
+            Screen::Menu(page, ref mut menu) => {
+                Nav::from_key(key).and_then(|nav| menu.on_nav(nav).map(|c| (page, c)))
+            }
+            Screen::Game(GameState::Start) => {
+                if key == 0x20 {
+                    self.screen = Screen::Game(match self.game.race() {
+                        Some(_) => GameState::Countdown(COUNTDOWN),
+                        None => GameState::Playing,
+                    });
+                }
+                None
+            }
+            Screen::Game(GameState::Countdown(_)) => None,
+            Screen::Game(GameState::Playing) => {
+                self.on_key(key);
+                None
+            }
+            Screen::Game(GameState::GameOver) => {
+                // Practice goes on from a placement undone.
+                if self.on_command(key) && !self.game.is_over() {
+                    self.screen = Screen::Game(GameState::Playing);
+                } else if Nav::from_key(key) == Some(Nav::Select) {
+                    self.screen = Screen::Summary;
+                }
+                None
+            }
+            Screen::Summary => {
+                if Nav::from_key(key) == Some(Nav::Select) {
+                    self.screen = match (self.editor.take(), &self.pending) {
+                        (Some(editor), _) => Screen::Editor(editor),
+                        (None, Some((rules, entry))) => {
+                            screen::result(&self.scores, rules, Some(entry))
+                        }
+                        (None, None) => screen::title(),
+                    };
+                }
+                None
+            }
+            Screen::Scores(_) => {
+                self.on_board_key(key);
+                None
+            }
+            Screen::Editor(ref mut editor) => {
+                editor.message = None;
+                if let Some(request) = editor.on_key(key) {
+                    self.on_request(request);
                 }
+                None
+            }
+        };
+        if let Some((page, choice)) = choice {
+            self.on_choice(page, choice);
+        }
+    }
+
+    /// Types the name of a new record, or browses the tables.
+    fn on_board_key(&mut self, key: i32) {
+        let (table, tables) = match self.screen {
+            Screen::Scores(ref mut board) => {
+                if let Some(ref mut name) = board.typing {
+                    match key {
+                        KEY_ENTER | 0x0a | 0x0d => {}
+                        KEY_BACKSPACE | 0x08 | 0x7f => {
+                            name.pop();
+                            return;
+                        }
+                        0x20..=0x7e if name.len() < NAME_LENGTH => {
+                            name.push(key as u8 as char);
+                            return;
+                        }
+                        _ => return,
+                    }
+                    let name = name.trim().to_string();
+                    return self.save_record(name);
+                }
+                (board.table, board.tables.len())
+            }
+            _ => return,
+        };
+        match Nav::from_key(key) {
+            Some(Nav::Left) if tables > 1 => {
+                self.screen = screen::scores(&self.scores, (table + tables - 1) % tables)
+            }
+            Some(Nav::Right) if tables > 1 => {
+                self.screen = screen::scores(&self.scores, (table + 1) % tables)
             }
+            Some(Nav::Select) | Some(Nav::Back) => self.screen = screen::title(),
+            _ => {}
         }
     }
 
+    /// Enters the pending record under `name` and saves the scores.
+    fn save_record(&mut self, name: String) {
+        let (rules, mut entry) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        if !name.is_empty() {
+            entry.name = name.clone();
+            self.name = name;
+        }
+        let rank = self.scores.insert(&rules, entry.clone());
+        let saved = self.scores.save(&Scores::path());
+        if let Screen::Scores(ref mut board) = self.screen {
+            board.typing = None;
+            if let Some(rank) = rank {
+                board.entries[rank] = entry;
+            }
+            board.message = Some(match saved {
+                Ok(()) => "saved, enter to go on".to_string(),
+                Err(e) => e,
+            });
+        }
+    }
+
+    /// Whether keys are being typed as text, so none of them quits.
+    fn is_typing(&self) -> bool {
+        match self.screen {
+            Screen::Scores(ref board) => board.typing.is_some(),
+            _ => self.binding.is_some(),
+        }
+    }
+
+    /// Moves the human players, each with their own keys. A key no player
+    /// has bound is taken for a practice command.
+    fn on_key(&mut self, key: i32) {
+        let humans = self.humans();
+        let seats = self.settings.keys.iter().zip(self.shifts.iter_mut());
+        let mut bound = false;
+        for ((keys, shift), &player) in seats.zip(humans.iter()) {
+            let action = match keys.action(key) {
+                Some(action) => action,
+                None => continue,
+            };
+            bound = true;
+            let moves = match action {
+                Action::Left | Action::Right => shift.press(action),
+                _ => true,
+            };
+            if moves {
+                self.game.on_action(player, action);
+            }
+        }
+        if !bound {
+            self.on_command(key);
+        }
+    }
+
+    /// Plays from, saves or leaves the board being edited.
+    fn on_request(&mut self, request: Request) {
+        let mut editor = match mem::replace(&mut self.screen, screen::title()) {
+            Screen::Editor(editor) => editor,
+            screen => {
+                self.screen = screen;
+                return;
+            }
+        };
+        let saved = match request {
+            Request::Back => return,
+            Request::Play => match editor.game() {
+                Ok(game) => {
+                    self.start(game, 0);
+                    self.editor = Some(editor);
+                    return;
+                }
+                Err(e) => Err(e),
+            },
+            Request::SaveField => save_field(&editor.lines()),
+            Request::SavePuzzle => self.save_puzzle(&editor),
+        };
+        editor.message = Some(match saved {
+            Ok(path) => format!("saved {}", path.display()),
+            Err(e) => e,
+        });
+        self.screen = Screen::Editor(editor);
+    }
+
+    /// Saves the board being edited as a puzzle, which the puzzle menu
+    /// lists from now on.
+    fn save_puzzle(&mut self, editor: &Editor) -> Result<PathBuf, String> {
+        let edited = self.puzzles.len() - Puzzle::builtin().len();
+        let puzzle = editor.puzzle(&format!("Edited {}", edited + 1))?;
+        let path = paths::puzzle_dir().join(format!("{}.json", now()));
+        puzzle.save(&path)?;
+        self.puzzles.push(puzzle);
+        Ok(path)
+    }
+
+    /// Applies the practice command of `key` to the first player, returning
+    /// whether it changed anything.
+    fn on_command(&mut self, key: i32) -> bool {
+        match (Command::from_key(key), self.game.players_mut().first_mut()) {
+            (Some(command), Some(player)) => player.on_command(command),
+            _ => false,
+        }
+    }
+
+    /// Seats played from the keyboard, in the order of the key settings.
+    fn humans(&self) -> Vec<usize> {
+        (0..self.game.players().len())
+            .filter(|&i| matches!(*self.game.players()[i].controller(), Controller::Human))
+            .collect()
+    }
+
+    /// Moves the humans still holding left or right.
+    fn shift(&mut self) {
+        let humans = self.humans();
+        for (shift, &player) in self.shifts.iter_mut().zip(humans.iter()) {
+            if let Some((action, n)) = shift.on_frame() {
+                for _ in 0..n {
+                    self.game.on_action(player, action);
+                }
+            }
+        }
+    }
+
+    /// Binds the key just pressed to the setting being changed.
+    fn bind(&mut self, section: &str, name: &str, key: i32) {
+        if key == 0x1b {
+            return self.set_message(String::new());
+        }
+        match key_name(key) {
+            Some(value) => self.change_setting(section, name, &value),
+            None => self.set_message("that key cannot be bound".to_string()),
+        }
+    }
+
+    /// Changes a setting from the Settings menu and saves them all. Values
+    /// that do not work leave the settings as they were.
+    fn change_setting(&mut self, section: &str, name: &str, value: &str) {
+        let cursor = match self.screen {
+            Screen::Menu(_, ref menu) => menu.cursor(),
+            Screen::Game(_) | Screen::Summary | Screen::Scores(_) | Screen::Editor(_) => return,
+        };
+        let (mut settings, mut file) = (self.settings.clone(), self.file.clone());
+        let changed = settings
+            .set(section, name, value)
+            .and_then(|_| settings.check())
+            .and_then(|_| file.set(section, name, value))
+            .and_then(|_| settings.theme())
+            .and_then(|theme| file.save(&Settings::path()).map(|_| theme));
+        let error = match changed {
+            Ok(theme) => {
+                self.view.set_theme(theme);
+                self.settings = settings;
+                self.file = file;
+                None
+            }
+            Err(e) => Some(e),
+        };
+        self.screen = screen::settings(&self.settings, cursor);
+        if let Some(e) = error {
+            self.set_message(e);
+        }
+    }
+
+    fn on_choice(&mut self, page: Page, choice: Choice) {
+        let (label, menu) = match self.screen {
+            Screen::Menu(_, ref menu) => {
+                let label = match choice {
+                    Choice::Selected(i) | Choice::Changed(i) => menu.entries()[i].label.clone(),
+                    Choice::Back => String::new(),
+                };
+                (label, menu.clone())
+            }
+            Screen::Game(_) | Screen::Summary | Screen::Scores(_) | Screen::Editor(_) => return,
+        };
+        match (page, choice) {
+            (Page::Title, Choice::Back) => {}
+            (_, Choice::Back) => self.screen = screen::title(),
+            (Page::Title, Choice::Selected(_)) => match label.as_str() {
+                "endless" => self.screen = screen::endless(),
+                "versus" => self.screen = screen::versus(Page::Versus, &self.settings),
+                "fever" => self.screen = screen::versus(Page::Fever, &self.settings),
+                "time attack" => self.screen = screen::race(Page::TimeAttack),
+                "sprint" => self.screen = screen::race(Page::Sprint),
+                "puzzle" => self.screen = screen::puzzles(&self.puzzles),
+                "mission" => self.screen = screen::missions(&self.missions, &self.campaign),
+                "editor" => {
+                    self.view.set_players(self.settings.size(), 1);
+                    self.screen = Screen::Editor(Box::new(Editor::new(self.settings.size())));
+                }
+                "practice" => {
+                    let controllers = vec![Controller::Human];
+                    let seed = rand::random();
+                    let game = new_game(Mode::Practice, controllers, &self.settings, seed, 1, None);
+                    self.start(game, seed);
+                }
+                "replay" => self.screen = screen::replays(&replay_names()),
+                "scores" => self.screen = screen::scores(&self.scores, 0),
+                "settings" => self.screen = screen::settings(&self.settings, 0),
+                _ => self.done = true,
+            },
+            (Page::Endless, Choice::Selected(_)) if label == "start" => {
+                let level = menu.value_of("level").parse().unwrap_or(1);
+                self.start_recorded(Mode::Endless, vec![Controller::Human], level, None);
+            }
+            (Page::TimeAttack, Choice::Selected(_)) | (Page::Sprint, Choice::Selected(_))
+                if label == "start" =>
+            {
+                let level = menu.value_of("level").parse().unwrap_or(1);
+                let (mode, goal) = if page == Page::Sprint {
+                    (Mode::Sprint, menu.value_of("goal").parse().ok())
+                } else {
+                    (Mode::TimeAttack, None)
+                };
+                self.start_recorded(mode, vec![Controller::Human], level, goal);
+            }
+            (Page::Versus, Choice::Selected(_)) | (Page::Fever, Choice::Selected(_))
+                if label == "start" =>
+            {
+                let level: Level = menu.value_of("cpu level").parse().unwrap_or(Level::Normal);
+                let opponent = match menu.value_of("opponent") {
+                    "human" => Controller::Human,
+                    _ => Controller::Cpu(Ai::new(level)),
+                };
+                let mode = if page == Page::Fever {
+                    Mode::Fever
+                } else {
+                    Mode::Versus
+                };
+                self.start_recorded(mode, vec![Controller::Human, opponent], 1, None);
+            }
+            (Page::Puzzle, Choice::Selected(i)) => {
+                let puzzle = self.puzzles[i].clone();
+                match puzzle.board() {
+                    Ok(field) => {
+                        let game = Game::new(vec![Player::new(field, Controller::Human)]);
+                        self.start(game.with_objective(puzzle.goal), 0);
+                    }
+                    Err(e) => self.set_message(e),
+                }
+            }
+            (Page::Mission, Choice::Selected(i)) if !self.campaign.is_open(i) => {
+                self.set_message("clear the missions before it first".to_string());
+            }
+            (Page::Mission, Choice::Selected(i)) => match self.missions[i].game() {
+                Ok(game) => {
+                    self.start(game, 0);
+                    self.mission = Some(i);
+                }
+                Err(e) => self.set_message(e),
+            },
+            (Page::Replay, Choice::Selected(_)) => {
+                let path = paths::replay_dir().join(&label);
+                match Replay::load(&path) {
+                    Ok(replay) => {
+                        self.start(replay.game(), replay.seed);
+                        // Watching a game again plays no new one.
+                        self.seats = None;
+                        self.screen = Screen::Game(GameState::Playing);
+                    }
+                    Err(e) => self.set_message(e),
+                }
+            }
+            (Page::Settings, Choice::Changed(_)) => {
+                if let Some((section, name)) = screen::setting(&label) {
+                    if !screen::is_key(section) {
+                        self.change_setting(section, name, menu.value_of(&label));
+                    }
+                }
+            }
+            (Page::Settings, Choice::Selected(_)) => match screen::setting(&label) {
+                Some((section, name)) if screen::is_key(section) => {
+                    self.binding = Some((section, name));
+                    self.set_message(format!("press a key for {}, esc to cancel", label));
+                }
+                Some(_) => {}
+                None => self.screen = screen::title(),
+            },
+            _ => {}
+        }
+    }
+
+    fn set_message(&mut self, message: String) {
+        if let Screen::Menu(_, ref mut menu) = self.screen {
+            menu.message = if message.is_empty() {
+                None
+            } else {
+                Some(message)
+            };
+        }
+    }
+
+    /// Starts a game that is saved as a replay when it ends.
+    fn start_recorded(
+        &mut self,
+        mode: Mode,
+        controllers: Vec<Controller>,
+        level: usize,
+        goal: Option<Objective>,
+    ) {
+        let seed = rand::random();
+        let game = new_game(mode, controllers, &self.settings, seed, level, goal);
+        self.start(game, seed);
+        self.track(mode, seed);
+    }
+
+    /// Saves the game dealt from `seed` as a replay when it ends, and ranks
+    /// the first player if they are human.
+    fn track(&mut self, mode: Mode, seed: u32) {
+        // Undone placements can be neither replayed nor ranked.
+        if mode == Mode::Practice {
+            return;
+        }
+        self.record = Some((seed, seat_names(&self.game)));
+        let players = self.game.players();
+        let mode = match self.game.race() {
+            Some(race) => race.name(),
+            None => mode.name().to_string(),
+        };
+        self.rules = players
+            .first()
+            .filter(|p| matches!(*p.controller(), Controller::Human))
+            .map(|p| Rules {
+                mode,
+                width: p.field().width(),
+                height: p.field().height(),
+                colors: p.field().colors(),
+                level: p.start_level(),
+            });
+    }
+
+    /// Starts `game`, dealt from `seed` if it was, whose stats are written
+    /// when it ends.
+    fn start(&mut self, game: Game, seed: u32) {
+        if let Some(player) = game.players().first() {
+            let size = Size::new(player.field().width(), player.field().height());
+            self.view.set_players(size, game.players().len());
+        }
+        self.seats = Some((seed, seat_names(&game)));
+        self.game = game;
+        self.record = None;
+        self.rules = None;
+        self.pending = None;
+        self.mission = None;
+        self.editor = None;
+        self.shifts = new_shifts(&self.settings);
+        self.screen = Screen::Game(GameState::Start);
+    }
+
+    /// Opens the next mission if the one just played was cleared. The
+    /// progress is kept for the session even if it cannot be saved.
+    fn save_campaign(&mut self) {
+        let mission = match self.mission.take() {
+            Some(mission) if self.game.is_cleared() == Some(true) => mission,
+            _ => return,
+        };
+        self.campaign.clear(mission);
+        let _ = self.campaign.save(&Campaign::path());
+    }
+
+    /// Writes the stats of every player of the game that just ended to the
+    /// stats directory, one JSON report per game. Failing to is not worth
+    /// interrupting the player for.
+    fn save_stats(&self) {
+        if let Some((seed, ref names)) = self.seats {
+            let _ = save_stats(&self.game, seed, names, &paths::stats_dir());
+        }
+    }
+
+    /// Keeps the game that just ended under the data directory. Failing to
+    /// is not worth interrupting the player for.
+    fn save_replay(&mut self) {
+        let (seed, names) = match self.record.take() {
+            Some(record) => record,
+            None => return,
+        };
+        let player = &self.game.players()[0];
+        let size = Size::new(player.field().width(), player.field().height());
+        let replay = Replay::new(seed, &size, names, &self.game);
+        let path = paths::replay_dir().join(format!("{}-{}.json", now(), seed));
+        let _ = replay.save(&path);
+    }
+
     fn main(&mut self) {
         while !self.done {
             match self.rx.try_recv() {
-                Ok(Event::MovePoyo(poyos)) => match self.state {
-                    GameState::Start => {}
-                    GameState::Playing { poyos: _ } => {
-                        self.state = GameState::Playing { poyos };
-                    }
-                    GameState::GameOver => {}
-                },
-                Ok(Event::Tick) => self.tick(),
                 Ok(Event::FrameUpdate) => self.on_frame(),
-                Ok(Event::Exit) => self.done = true,
-                Ok(Event::Input(i)) => match i {
-                    ncurses::KEY_LEFT => self.field.move_current(Direction::Left),
-                    ncurses::KEY_RIGHT => self.field.move_current(Direction::Right),
-                    ncurses::KEY_DOWN => self.field.move_current(Direction::Down),
-                    0x20 => match self.state {
-                        GameState::Start => {
-                            self.state = GameState::Playing { poyos: vec![] };
-                        }
-                        GameState::Playing { poyos: _ } => {
-                            self.field.rotate_current();
-                        }
-                        GameState::GameOver => {}
-                    },
-                    _ => {}
-                },
+                Ok(Event::Input(KEY_RESIZE)) => self.view.resize(),
+                Ok(Event::Input(QUIT)) if !self.is_typing() => self.done = true,
+                Ok(Event::Input(key)) => self.on_input(key),
                 _ => {}
             }
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{new_game, save_stats, seat_names};
+    use options::Mode;
+    use player::Controller;
+    use serde_json;
+    use settings::Settings;
+    use stats::Report;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_practice_writes_stats() {
+        // Practice is neither replayed nor ranked, but has stats all the same.
+        let settings = Settings::default();
+        let game = new_game(
+            Mode::Practice,
+            vec![Controller::Human],
+            &settings,
+            7,
+            1,
+            None,
+        );
+        let dir = env::temp_dir().join(format!("poyo-stats-{}", std::process::id()));
+        let path = save_stats(&game, 7, &seat_names(&game), &dir).unwrap();
+        let json = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let report: Report = serde_json::from_str(&json).unwrap();
+        assert_eq!(report.seed, 7);
+        assert_eq!(report.players.len(), 1);
+        assert_eq!(report.players[0].name, "human");
+    }
+}
@@ -0,0 +1,181 @@
+use ncurses::{KEY_BACKSPACE, KEY_DOWN, KEY_ENTER, KEY_LEFT, KEY_RIGHT, KEY_UP};
+
+/// A key as far as menus are concerned.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Nav {
+    Up,
+    Down,
+    /// Previous value of an option.
+    Left,
+    /// Next value of an option.
+    Right,
+    Select,
+    Back,
+}
+
+impl Nav {
+    pub fn from_key(key: i32) -> Option<Nav> {
+        match key {
+            KEY_UP => Some(Nav::Up),
+            KEY_DOWN => Some(Nav::Down),
+            KEY_LEFT => Some(Nav::Left),
+            KEY_RIGHT => Some(Nav::Right),
+            KEY_ENTER | 0x0a | 0x0d | 0x20 => Some(Nav::Select),
+            KEY_BACKSPACE | 0x1b | 0x7f => Some(Nav::Back),
+            _ => None,
+        }
+    }
+}
+
+/// What a key did to a menu, when the caller has something to do about it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Choice {
+    /// The entry at this index was selected.
+    Selected(usize),
+    /// The option at this index took another value.
+    Changed(usize),
+    Back,
+}
+
+/// A line of a menu. Options cycle through their values with left and
+/// right; plain entries have none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub label: String,
+    pub values: Vec<String>,
+    pub selected: usize,
+}
+
+impl Entry {
+    pub fn text(&self) -> String {
+        match self.values.get(self.selected) {
+            Some(value) => format!("{:<12} < {} >", self.label, value),
+            None => self.label.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Menu {
+    pub title: String,
+    entries: Vec<Entry>,
+    cursor: usize,
+    /// Shown under the entries, for errors and hints.
+    pub message: Option<String>,
+}
+
+impl Menu {
+    pub fn new(title: &str) -> Self {
+        Menu {
+            title: title.to_string(),
+            entries: vec![],
+            cursor: 0,
+            message: None,
+        }
+    }
+
+    pub fn entry(mut self, label: &str) -> Self {
+        self.entries.push(Entry {
+            label: label.to_string(),
+            values: vec![],
+            selected: 0,
+        });
+        self
+    }
+
+    /// An option showing `values`, on the one equal to `value` if any.
+    pub fn option<S: AsRef<str>>(mut self, label: &str, values: &[S], value: &str) -> Self {
+        let values: Vec<String> = values.iter().map(|v| v.as_ref().to_string()).collect();
+        let selected = values.iter().position(|v| v == value).unwrap_or(0);
+        self.entries.push(Entry {
+            label: label.to_string(),
+            values,
+            selected,
+        });
+        self
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, cursor: usize) {
+        self.cursor = cursor.min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Current value of the option at `index`.
+    pub fn value(&self, index: usize) -> &str {
+        let entry = &self.entries[index];
+        entry
+            .values
+            .get(entry.selected)
+            .map(|v| v.as_str())
+            .unwrap_or("")
+    }
+
+    /// Current value of the option labelled `label`, empty if there is
+    /// no such option.
+    pub fn value_of(&self, label: &str) -> &str {
+        match self.entries.iter().position(|e| e.label == label) {
+            Some(index) => self.value(index),
+            None => "",
+        }
+    }
+
+    pub fn on_nav(&mut self, nav: Nav) -> Option<Choice> {
+        let len = self.entries.len();
+        if len == 0 {
+            return if nav == Nav::Back {
+                Some(Choice::Back)
+            } else {
+                None
+            };
+        }
+        let entry = &mut self.entries[self.cursor];
+        let values = entry.values.len();
+        match nav {
+            Nav::Up => self.cursor = (self.cursor + len - 1) % len,
+            Nav::Down => self.cursor = (self.cursor + 1) % len,
+            Nav::Left if values > 0 => {
+                entry.selected = (entry.selected + values - 1) % values;
+                return Some(Choice::Changed(self.cursor));
+            }
+            Nav::Right if values > 0 => {
+                entry.selected = (entry.selected + 1) % values;
+                return Some(Choice::Changed(self.cursor));
+            }
+            Nav::Left | Nav::Right => {}
+            Nav::Select => return Some(Choice::Selected(self.cursor)),
+            Nav::Back => return Some(Choice::Back),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Choice, Menu, Nav};
+
+    #[test]
+    fn test_navigation() {
+        let mut menu = Menu::new("versus")
+            .option("cpu", &["easy", "normal", "hard"], "normal")
+            .entry("start");
+        assert_eq!(menu.value(0), "normal");
+        assert_eq!(menu.on_nav(Nav::Right), Some(Choice::Changed(0)));
+        assert_eq!(menu.on_nav(Nav::Right), Some(Choice::Changed(0)));
+        assert_eq!(menu.value(0), "easy");
+        assert_eq!(menu.value_of("cpu"), "easy");
+        assert_eq!(menu.value_of("start"), "");
+        assert_eq!(menu.on_nav(Nav::Up), None);
+        assert_eq!(menu.cursor(), 1);
+        assert_eq!(menu.on_nav(Nav::Left), None);
+        assert_eq!(menu.on_nav(Nav::Select), Some(Choice::Selected(1)));
+        assert_eq!(menu.on_nav(Nav::Back), Some(Choice::Back));
+        assert_eq!(menu.entries()[0].text(), "cpu          < easy >");
+    }
+}
@@ -0,0 +1,183 @@
+//! Missions: a campaign of objectives played one after the other, each
+//! opening once the one before it is cleared. A mission may start from a
+//! board and deal exact pairs like a puzzle, and may add a clock and
+//! garbage falling from the sky:
+//!
+//! ```json
+//! { "name": "Garbage rain", "goal": "survive", "seconds": 60,
+//!   "rain": { "every": 10, "amount": 6 } }
+//! ```
+//!
+//! Without pairs, random ones are dealt. Progress is kept in
+//! `$XDG_DATA_HOME/poyo/missions.json`.
+
+use std::path::{Path, PathBuf};
+
+use field::Field;
+use game::Game;
+use paths;
+use player::{Controller, Player};
+use puzzle::{self, Objective};
+use race::Race;
+use serde_json;
+
+/// Garbage falling on every player every so often.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rain {
+    /// Seconds between falls.
+    pub every: u64,
+    pub amount: usize,
+}
+
+impl Rain {
+    /// Whether garbage falls at `frame`.
+    pub fn is_due(&self, frame: u64) -> bool {
+        self.every > 0 && frame % (self.every * 100) == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mission {
+    pub name: String,
+    /// Starting board, in the field text form; empty for an empty field.
+    #[serde(default)]
+    pub field: Vec<String>,
+    /// Pivot then child, as color letters; empty for random pairs.
+    #[serde(default)]
+    pub pairs: Vec<String>,
+    pub goal: Objective,
+    /// Time allowed, if the mission is against the clock.
+    #[serde(default)]
+    pub seconds: Option<u64>,
+    #[serde(default)]
+    pub rain: Option<Rain>,
+}
+
+impl Mission {
+    pub fn builtin() -> Vec<Mission> {
+        serde_json::from_str(include_str!("../puzzles/missions.json"))
+            .expect("the built-in missions are valid")
+    }
+
+    pub fn describe(&self) -> String {
+        match (self.goal, self.seconds) {
+            (Objective::Survive, Some(s)) => format!("survive {} seconds", s),
+            (goal, Some(s)) => format!("{} in {} seconds", goal.describe(), s),
+            (goal, None) => goal.describe(),
+        }
+    }
+
+    /// The game the first player has to clear.
+    pub fn game(&self) -> Result<Game, String> {
+        if self.goal == Objective::Survive && self.seconds.is_none() {
+            return Err(format!("{}: surviving needs a time limit", self.name));
+        }
+        let mut field = if self.field.is_empty() {
+            Field::default()
+        } else {
+            puzzle::board(&self.field)?
+        };
+        if !self.pairs.is_empty() {
+            field.set_pairs(puzzle::pairs(&self.pairs)?);
+        }
+        let mut game =
+            Game::new(vec![Player::new(field, Controller::Human)]).with_objective(self.goal);
+        if let Some(seconds) = self.seconds {
+            game = game.with_race(Race::Attack(seconds * 100));
+        }
+        if let Some(rain) = self.rain {
+            game = game.with_rain(rain);
+        }
+        Ok(game)
+    }
+}
+
+/// How far the player got through the missions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Campaign {
+    /// Missions cleared, in order from the first.
+    pub cleared: usize,
+}
+
+impl Campaign {
+    pub fn path() -> PathBuf {
+        paths::data_dir().join("missions.json")
+    }
+
+    /// The progress in `path`, or none if there is no such file yet.
+    pub fn load(path: &Path) -> Result<Campaign, String> {
+        paths::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        paths::save_json(path, self)
+    }
+
+    /// Whether mission `n` (0-origin) can be played.
+    pub fn is_open(&self, n: usize) -> bool {
+        n <= self.cleared
+    }
+
+    /// Notes mission `n` as cleared, opening the next one.
+    pub fn clear(&mut self, n: usize) {
+        self.cleared = self.cleared.max(n + 1);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Campaign, Mission, Rain};
+    use puzzle::Objective;
+
+    #[test]
+    fn test_builtin_missions_start() {
+        for mission in Mission::builtin() {
+            let game = mission.game().unwrap();
+            assert_eq!(game.objective(), Some(mission.goal), "{}", mission.name);
+            assert_eq!(game.race().is_some(), mission.seconds.is_some());
+        }
+        assert_eq!("30 garbage".parse(), Ok(Objective::Garbage(30)));
+        assert_eq!("3 colors".parse(), Ok(Objective::Colors(3)));
+        assert_eq!("survive".parse(), Ok(Objective::Survive));
+    }
+
+    #[test]
+    fn test_survive_the_rain() {
+        let mission = Mission {
+            name: "drizzle".to_string(),
+            field: vec![],
+            pairs: vec![],
+            goal: Objective::Survive,
+            seconds: Some(2),
+            rain: Some(Rain {
+                every: 1,
+                amount: 3,
+            }),
+        };
+        let mut game = mission.game().unwrap();
+        game.on_frame();
+        assert_eq!(game.is_cleared(), Some(false));
+        while !game.is_over() {
+            game.on_frame();
+        }
+        assert_eq!(game.frame(), 200);
+        assert_eq!(game.is_cleared(), Some(true));
+        assert_eq!(game.players()[0].stats().garbage_received, 6);
+        let mission = Mission {
+            seconds: None,
+            ..mission
+        };
+        assert!(mission.game().is_err());
+    }
+
+    #[test]
+    fn test_campaign_opens_in_order() {
+        let mut campaign = Campaign::default();
+        assert!(campaign.is_open(0));
+        assert!(!campaign.is_open(1));
+        campaign.clear(0);
+        campaign.clear(0);
+        assert!(campaign.is_open(1));
+        assert!(!campaign.is_open(2));
+    }
+}
@@ -0,0 +1,353 @@
+use std::str::FromStr;
+
+use ai::Level;
+use puzzle::Objective;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// The title menu, to pick a mode from.
+    Menu,
+    Endless,
+    /// A human against the CPU.
+    Versus,
+    /// Versus by fever rules.
+    Fever,
+    /// As many points as possible in two minutes.
+    TimeAttack,
+    /// A goal as fast as possible.
+    Sprint,
+    /// No gravity, and placements can be undone.
+    Practice,
+    /// The CPU playing alone, as a demo.
+    WatchAi,
+    /// Headless versus matches between two bots or CPUs.
+    Tournament,
+    /// Times the field backends against each other.
+    Bench,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+    /// The modes that can be played from the title menu or started
+    /// directly.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "menu" => Ok(Mode::Menu),
+            "endless" => Ok(Mode::Endless),
+            "versus" => Ok(Mode::Versus),
+            "fever" => Ok(Mode::Fever),
+            "time-attack" => Ok(Mode::TimeAttack),
+            "sprint" => Ok(Mode::Sprint),
+            "practice" => Ok(Mode::Practice),
+            "watch-ai" => Ok(Mode::WatchAi),
+            _ => Err(format!("unknown mode: {}", s)),
+        }
+    }
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Menu => "menu",
+            Mode::Endless => "endless",
+            Mode::Versus => "versus",
+            Mode::Fever => "fever",
+            Mode::TimeAttack => "time-attack",
+            Mode::Sprint => "sprint",
+            Mode::Practice => "practice",
+            Mode::WatchAi => "watch-ai",
+            Mode::Tournament => "tournament",
+            Mode::Bench => "bench",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub mode: Mode,
+    pub level: Level,
+    /// Commands of external bots taking the players' seats.
+    pub bots: Vec<String>,
+    /// Number of matches in a tournament.
+    pub games: usize,
+    /// Rounds of each benchmark.
+    pub iterations: usize,
+    pub seed: Option<u32>,
+    /// Directory the tournament writes its replays to.
+    pub replays: Option<String>,
+    /// Name of a built-in theme or path of a theme file.
+    pub theme: String,
+    /// Draw poyos with shapes and colorblind-safe colors over the theme.
+    pub colorblind: bool,
+    /// Draw connected poyos as one piece when the theme can.
+    pub joined: bool,
+    /// Number of colors pairs are dealt from, over the settings file's.
+    pub colors: Option<usize>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    /// What a sprint is run for, instead of `race::SPRINT_GOAL`.
+    pub goal: Option<Objective>,
+    /// Replay file to play back instead of a new game.
+    pub replay: Option<String>,
+    /// Play to the end without a view and print the fields.
+    pub headless: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            mode: Mode::Menu,
+            level: Level::Normal,
+            bots: vec![],
+            games: 100,
+            iterations: 100,
+            seed: None,
+            replays: None,
+            theme: "classic".to_string(),
+            colorblind: false,
+            joined: false,
+            colors: None,
+            width: None,
+            height: None,
+            goal: None,
+            replay: None,
+            headless: false,
+        }
+    }
+}
+
+pub const USAGE: &str = "usage: curses [--menu | --endless | --versus | --fever | --watch-ai]
+                [--time-attack | --sprint [--goal GOAL] | --practice]
+                [--mode MODE]
+                [--level easy|normal|hard] [--bot CMD]... [--seed N]
+                [--colors N] [--width N] [--height N] [--replay FILE]
+                [--theme NAME|FILE] [--colorblind] [--joined] [--headless]
+       curses tournament [--games N] [--seed N] [--replays DIR]
+                         [--level easy|normal|hard] [--bot CMD]...
+
+  Defaults are read from $XDG_CONFIG_HOME/poyo/settings.ini, which the
+  settings menu writes; the flags below override them.
+
+  --menu         open the title menu, whatever the settings say
+  --endless      start a game alone right away, going on until the field
+                 fills up, instead of opening the title menu. Given bots
+                 and no mode, this is the mode they play
+  --fever        versus where offsetting garbage fills a gauge that swaps
+                 the field for chain patterns against the clock
+  --time-attack  score as much as possible in two minutes
+  --sprint       reach the goal as fast as possible
+  --goal GOAL    points, as 10000 (the default), or a chain, as 5-chain
+  --practice     play without gravity, dropping pieces with down. z undoes
+                 a placement, y redoes it, r deals another next pair and n
+                 steps through the next pairs to choose one, unless the
+                 key is bound to a move
+  --mode MODE    menu, endless, versus, fever, time-attack, sprint,
+                 practice or watch-ai
+  --seed N       seed the pairs are dealt from, to play a game again
+  --colors N     deal pairs of 3 or 4 colors
+  --width N      columns of the field, 3 to 16
+  --height N     rows of the field, 4 to 24
+  --replay FILE  play back a saved replay
+  --headless     play to the end without a view and print the fields.
+                 Every seat must be the CPU or a bot, or a replay given
+  --theme NAME   classic (default), ascii, blocks, circles, colorblind, or
+                 the path of a JSON theme file
+  --colorblind   tell poyos apart by shape (● ▲ ■ ◆, ★ for garbage) and
+                 use colorblind-safe colors, whatever the theme
+  --joined       draw connected poyos as one piece (blocks and circles
+                 themes; others, and --colorblind, draw single glyphs)
+  --bot CMD      spawn CMD as a player. It takes the only seat, or the CPU's
+                 seat in versus and fever. Given twice there, two bots play
+                 each other.
+  tournament     play versus matches with no view and report the results.
+                 Missing bots are replaced by the CPU at --level.
+  --games N      number of matches (default 100)
+  --seed N       seed of the first match
+  --replays DIR  write the replay of each match to DIR
+       curses bench [--iterations N] [--seed N]
+
+  bench          time the bitboard backend against the field on random
+                 boards, averaging N rounds (default 100)";
+
+impl Options {
+    pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Options, String> {
+        Options::parse_over(Options::default(), args)
+    }
+
+    /// Parses `args` over `defaults`, which come from the settings file.
+    pub fn parse_over<I: Iterator<Item = String>>(
+        defaults: Options,
+        args: I,
+    ) -> Result<Options, String> {
+        let mut options = defaults;
+        let mut args = args.peekable();
+        let subcommand = match args.peek().map(|a| a.as_str()) {
+            Some("tournament") => Some(Mode::Tournament),
+            Some("bench") => Some(Mode::Bench),
+            _ => None,
+        };
+        if let Some(mode) = subcommand {
+            options.mode = mode;
+            args.next();
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--menu" => options.mode = Mode::Menu,
+                "--endless" => options.mode = Mode::Endless,
+                "--versus" => options.mode = Mode::Versus,
+                "--fever" => options.mode = Mode::Fever,
+                "--watch-ai" => options.mode = Mode::WatchAi,
+                "--time-attack" => options.mode = Mode::TimeAttack,
+                "--sprint" => options.mode = Mode::Sprint,
+                "--practice" => options.mode = Mode::Practice,
+                "--goal" => {
+                    let value = args.next().ok_or("--goal needs a value")?;
+                    options.goal = Some(value.parse()?);
+                }
+                "--level" => {
+                    let value = args.next().ok_or("--level needs a value")?;
+                    options.level = value.parse()?;
+                }
+                "--bot" => {
+                    let value = args.next().ok_or("--bot needs a command")?;
+                    if options.bots.len() == 2 {
+                        return Err("at most two bots can play".to_string());
+                    }
+                    options.bots.push(value);
+                }
+                "--games" => {
+                    let value = args.next().ok_or("--games needs a number")?;
+                    options.games = parse_number(&arg, &value)?;
+                }
+                "--seed" => {
+                    let value = args.next().ok_or("--seed needs a number")?;
+                    options.seed = Some(parse_number(&arg, &value)?);
+                }
+                "--iterations" => {
+                    let value = args.next().ok_or("--iterations needs a number")?;
+                    options.iterations = parse_number(&arg, &value)?;
+                }
+                "--replays" => {
+                    let value = args.next().ok_or("--replays needs a directory")?;
+                    options.replays = Some(value);
+                }
+                "--mode" => {
+                    let value = args.next().ok_or("--mode needs a value")?;
+                    options.mode = value.parse()?;
+                }
+                "--colors" | "--width" | "--height" => {
+                    let value = args.next().ok_or(format!("{} needs a number", arg))?;
+                    let n = Some(parse_number(&arg, &value)?);
+                    match arg.as_str() {
+                        "--colors" => options.colors = n,
+                        "--width" => options.width = n,
+                        _ => options.height = n,
+                    }
+                }
+                "--replay" => {
+                    let value = args.next().ok_or("--replay needs a file")?;
+                    options.replay = Some(value);
+                }
+                "--headless" => options.headless = true,
+                "--colorblind" => options.colorblind = true,
+                "--joined" => options.joined = true,
+                "--theme" => {
+                    options.theme = args.next().ok_or("--theme needs a name or file")?;
+                }
+                _ => return Err(format!("unknown argument: {}", arg)),
+            }
+        }
+        if options.mode == Mode::Menu && !options.bots.is_empty() {
+            options.mode = Mode::Endless;
+        }
+        Ok(options)
+    }
+}
+
+fn parse_number<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} expects a number, got {}", flag, value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Mode, Options};
+    use ai::Level;
+    use puzzle::Objective;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+        let options = parse(&["--versus", "--level", "hard"]).unwrap();
+        assert_eq!(options.mode, Mode::Versus);
+        assert_eq!(options.level, Level::Hard);
+        assert_eq!(parse(&["--watch-ai"]).unwrap().mode, Mode::WatchAi);
+        assert_eq!(parse(&["--endless"]).unwrap().mode, Mode::Endless);
+        assert_eq!(parse(&["--mode", "fever"]).unwrap().mode, Mode::Fever);
+        assert_eq!(parse(&["--time-attack"]).unwrap().mode, Mode::TimeAttack);
+        let options = parse(&["--sprint", "--goal", "5-chain"]).unwrap();
+        assert_eq!(options.mode, Mode::Sprint);
+        assert_eq!(options.goal, Some(Objective::Chain(5)));
+        let options = parse(&["--bot", "./a", "--bot", "./b"]).unwrap();
+        assert_eq!(options.bots, vec!["./a", "./b"]);
+        let options = parse(&["tournament", "--games", "10", "--seed", "3"]).unwrap();
+        assert_eq!(options.mode, Mode::Tournament);
+        assert_eq!(options.games, 10);
+        assert_eq!(options.seed, Some(3));
+        let options = parse(&["bench", "--iterations", "5"]).unwrap();
+        assert_eq!(options.mode, Mode::Bench);
+        assert_eq!(options.iterations, 5);
+        assert_eq!(parse(&["--theme", "blocks"]).unwrap().theme, "blocks");
+        assert!(parse(&["--colorblind"]).unwrap().colorblind);
+        assert!(parse(&["--joined"]).unwrap().joined);
+        let options = parse(&["--mode", "watch-ai", "--width", "8", "--colors", "3"]).unwrap();
+        assert_eq!(options.mode, Mode::WatchAi);
+        assert_eq!((options.width, options.height), (Some(8), None));
+        assert_eq!(options.colors, Some(3));
+        let options = parse(&["--replay", "a.json", "--headless"]).unwrap();
+        assert_eq!(options.replay.as_deref(), Some("a.json"));
+        assert!(options.headless);
+        let defaults = Options {
+            mode: Mode::Versus,
+            theme: "blocks".to_string(),
+            ..Options::default()
+        };
+        let args = ["--endless"].iter().map(|s| s.to_string());
+        let options = Options::parse_over(defaults.clone(), args).unwrap();
+        assert_eq!(
+            (options.mode, options.theme.as_str()),
+            (Mode::Endless, "blocks")
+        );
+        let args = ["bench"].iter().map(|s| s.to_string());
+        assert_eq!(
+            Options::parse_over(defaults.clone(), args).unwrap().mode,
+            Mode::Bench
+        );
+        let args = ["--menu"].iter().map(|s| s.to_string());
+        assert_eq!(
+            Options::parse_over(defaults, args).unwrap().mode,
+            Mode::Menu
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(parse(&["--level"]).is_err());
+        assert!(parse(&["--level", "insane"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+        assert!(parse(&["--bot"]).is_err());
+        assert!(parse(&["--theme"]).is_err());
+        assert!(parse(&["--mode", "tournament"]).is_err());
+        assert!(parse(&["--width", "wide"]).is_err());
+        assert!(parse(&["--replay"]).is_err());
+        assert!(parse(&["--goal", "soon"]).is_err());
+        assert!(parse(&["tournament", "--games", "many"]).is_err());
+        assert!(parse(&["--versus", "tournament"]).is_err());
+        assert!(parse(&["--bot", "a", "--bot", "b", "--bot", "c"]).is_err());
+    }
+}
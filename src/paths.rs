@@ -0,0 +1,108 @@
+//! Where files are kept, after the XDG base directory specification.
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+
+/// `$XDG_DATA_HOME/poyo`: replays, records and stats.
+pub fn data_dir() -> PathBuf {
+    dir(
+        env::var_os("XDG_DATA_HOME"),
+        env::var_os("HOME"),
+        ".local/share",
+    )
+}
+
+/// `$XDG_CONFIG_HOME/poyo`: settings.
+pub fn config_dir() -> PathBuf {
+    dir(
+        env::var_os("XDG_CONFIG_HOME"),
+        env::var_os("HOME"),
+        ".config",
+    )
+}
+
+pub fn replay_dir() -> PathBuf {
+    data_dir().join("replays")
+}
+
+/// Stats of every recorded game, one JSON file each.
+pub fn stats_dir() -> PathBuf {
+    data_dir().join("stats")
+}
+
+/// Puzzles saved from the editor, shown after the built-in ones.
+pub fn puzzle_dir() -> PathBuf {
+    data_dir().join("puzzles")
+}
+
+/// Boards saved from the editor in the field text form.
+pub fn field_dir() -> PathBuf {
+    data_dir().join("fields")
+}
+
+/// The JSON in `path`, which must exist.
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_json(path, &json)
+}
+
+/// The JSON in `path`, or the default if there is no such file yet.
+pub fn load_json<T: DeserializeOwned + Default>(path: &Path) -> Result<T, String> {
+    match fs::read_to_string(path) {
+        Ok(json) => parse_json(path, &json),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Writes `value` to `path` as pretty JSON, making its directory first.
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(path, json + "\n").map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
+
+fn parse_json<T: DeserializeOwned>(path: &Path, json: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// The spec says an unset or empty variable means `$HOME/fallback`.
+fn dir(xdg: Option<OsString>, home: Option<OsString>, fallback: &str) -> PathBuf {
+    let base = match xdg {
+        Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(home.unwrap_or_default()).join(fallback),
+    };
+    base.join("poyo")
+}
+
+#[cfg(test)]
+mod test {
+    use super::dir;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_dir() {
+        let home = Some("/home/p".into());
+        assert_eq!(
+            dir(Some("/xdg".into()), home.clone(), ".config"),
+            PathBuf::from("/xdg/poyo")
+        );
+        assert_eq!(
+            dir(Some("".into()), home.clone(), ".config"),
+            PathBuf::from("/home/p/.config/poyo")
+        );
+        assert_eq!(
+            dir(None, home, ".local/share"),
+            PathBuf::from("/home/p/.local/share/poyo")
+        );
+    }
+}
@@ -0,0 +1,243 @@
+//! Falling pieces. Pairs turn their child around the pivot; the larger
+//! pieces fill three or four cells of a 2x2 box and turn inside it, except
+//! the big one, which changes color instead.
+
+use std::cmp::{max, min};
+
+use color::Color;
+use direction::Direction;
+use position::Position;
+use poyo::Poyo;
+use poyopoyo::PoyoPoyo;
+use rand::Rng;
+
+/// Cells of the box from the top left, clockwise.
+const BOX: [(i32, i32); 4] = [(0, 0), (1, 0), (1, 1), (0, 1)];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Pair,
+    /// An L of three: two of one color stacked, one of another beside.
+    Triple,
+    /// Four, two of each color side by side, or all of one.
+    Quad,
+    /// Four of one color that changes color when rotated.
+    Big,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Piece {
+    Pair(PoyoPoyo),
+    Boxed {
+        shape: Shape,
+        /// Top left of the box.
+        corner: Position,
+        /// Color of each cell of the box, in the order of `BOX`.
+        cells: [Option<Color>; 4],
+    },
+}
+
+impl From<PoyoPoyo> for Piece {
+    fn from(pair: PoyoPoyo) -> Self {
+        Piece::Pair(pair)
+    }
+}
+
+impl Piece {
+    /// An L at the spawn position: `stacked` twice on the left, `side`
+    /// on the bottom right.
+    pub fn triple(stacked: Color, side: Color) -> Self {
+        Piece::boxed(
+            Shape::Triple,
+            [Some(stacked), None, Some(side), Some(stacked)],
+        )
+    }
+
+    /// A 2x2 at the spawn position, `left` on the left and `right` on the
+    /// right.
+    pub fn quad(left: Color, right: Color) -> Self {
+        Piece::boxed(
+            Shape::Quad,
+            [Some(left), Some(right), Some(right), Some(left)],
+        )
+    }
+
+    pub fn big(color: Color) -> Self {
+        Piece::boxed(Shape::Big, [Some(color); 4])
+    }
+
+    fn boxed(shape: Shape, cells: [Option<Color>; 4]) -> Self {
+        Piece::Boxed {
+            shape,
+            corner: Position::new(1, 0),
+            cells,
+        }
+    }
+
+    /// A piece of one of `shapes` in the first `colors` colors, drawn from
+    /// `rng`. Dealing pairs only draws exactly what `PoyoPoyo::rand_from`
+    /// does, so seeded games deal the same pairs as before.
+    pub fn rand_from<R: Rng>(rng: &mut R, colors: u8, shapes: &[Shape]) -> Self {
+        let shape = match shapes.len() {
+            0 | 1 => shapes.first().cloned().unwrap_or(Shape::Pair),
+            n => shapes[rng.gen::<usize>() % n],
+        };
+        let color = |rng: &mut R| Color::from(rng.gen::<u8>() % colors);
+        match shape {
+            Shape::Pair => Piece::Pair(PoyoPoyo::rand_from(rng, colors)),
+            Shape::Triple => {
+                let stacked = color(rng);
+                Piece::triple(stacked, color(rng))
+            }
+            Shape::Quad => {
+                let left = color(rng);
+                Piece::quad(left, color(rng))
+            }
+            Shape::Big => Piece::big(color(rng)),
+        }
+    }
+
+    pub fn shape(&self) -> Shape {
+        match *self {
+            Piece::Pair(_) => Shape::Pair,
+            Piece::Boxed { shape, .. } => shape,
+        }
+    }
+
+    /// The pair, if the piece is one.
+    pub fn pair(&self) -> Option<&PoyoPoyo> {
+        match *self {
+            Piece::Pair(ref pair) => Some(pair),
+            Piece::Boxed { .. } => None,
+        }
+    }
+
+    /// Every poyo of the piece, the pivot first for pairs.
+    pub fn poyos(&self) -> Vec<Poyo> {
+        match *self {
+            Piece::Pair(ref pair) => vec![pair.0, pair.1],
+            Piece::Boxed { corner, cells, .. } => BOX
+                .iter()
+                .zip(cells.iter())
+                .filter_map(|(&(dx, dy), cell)| {
+                    cell.map(|c| Poyo::new(Position::new(corner.x + dx, corner.y + dy), c))
+                })
+                .collect(),
+        }
+    }
+
+    pub fn moves(&mut self, d: Direction) {
+        match *self {
+            Piece::Pair(ref mut pair) => pair.moves(d),
+            Piece::Boxed { ref mut corner, .. } => {
+                let mut poyo = Poyo::new(*corner, Color::Red);
+                poyo.moves(d);
+                *corner = Position::new(poyo.x(), poyo.y());
+            }
+        }
+    }
+
+    /// The piece turned clockwise. The big piece stays put and takes the
+    /// next of the first `colors` colors instead.
+    pub fn rotated(&self, colors: usize) -> Piece {
+        let mut piece = self.clone();
+        match piece {
+            Piece::Pair(ref mut pair) => pair.rotate(),
+            Piece::Boxed {
+                shape: Shape::Big,
+                ref mut cells,
+                ..
+            } => {
+                let all = Color::all();
+                let colors = &all[..colors.max(1).min(all.len())];
+                let now = cells[0].and_then(|c| colors.iter().position(|&o| o == c));
+                let next = colors[now.map_or(0, |i| (i + 1) % colors.len())];
+                *cells = [Some(next); 4];
+            }
+            Piece::Boxed { ref mut cells, .. } => {
+                let old = *cells;
+                for (i, cell) in cells.iter_mut().enumerate() {
+                    *cell = old[(i + 3) % 4];
+                }
+            }
+        }
+        piece
+    }
+
+    /// Colors of the piece by row from its top, as wide as the piece.
+    pub fn grid(&self) -> Vec<Vec<Option<Color>>> {
+        let (left, top) = (self.left(), self.top());
+        let width = (self.right() - left + 1) as usize;
+        let mut grid = vec![vec![None; width]; (self.bottom() - top + 1) as usize];
+        for p in self.poyos() {
+            grid[(p.y() - top) as usize][(p.x() - left) as usize] = Some(p.color());
+        }
+        grid
+    }
+
+    pub fn left(&self) -> i32 {
+        self.poyos().iter().fold(i32::MAX, |m, p| min(m, p.x()))
+    }
+
+    pub fn right(&self) -> i32 {
+        self.poyos().iter().fold(i32::MIN, |m, p| max(m, p.x()))
+    }
+
+    pub fn top(&self) -> i32 {
+        self.poyos().iter().fold(i32::MAX, |m, p| min(m, p.y()))
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.poyos().iter().fold(i32::MIN, |m, p| max(m, p.y()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Piece, Shape};
+    use color::Color;
+    use direction::Direction::*;
+
+    fn cells(piece: &Piece) -> Vec<(i32, i32, char)> {
+        piece
+            .poyos()
+            .iter()
+            .map(|p| (p.x(), p.y(), p.color().to_char()))
+            .collect()
+    }
+
+    #[test]
+    fn test_triple_turns_in_its_box() {
+        let mut piece = Piece::triple(Color::Red, Color::Blue);
+        assert_eq!(cells(&piece), vec![(1, 0, 'R'), (2, 1, 'B'), (1, 1, 'R')]);
+        piece.moves(Right);
+        piece = piece.rotated(4);
+        assert_eq!(cells(&piece), vec![(2, 0, 'R'), (3, 0, 'R'), (2, 1, 'B')]);
+        for _ in 0..3 {
+            piece = piece.rotated(4);
+        }
+        assert_eq!(piece, {
+            let mut p = Piece::triple(Color::Red, Color::Blue);
+            p.moves(Right);
+            p
+        });
+        assert_eq!((piece.left(), piece.right()), (2, 3));
+        assert_eq!((piece.top(), piece.bottom()), (0, 1));
+        let (r, b) = (Some(Color::Red), Some(Color::Blue));
+        assert_eq!(piece.grid(), vec![vec![r, None], vec![r, b]]);
+    }
+
+    #[test]
+    fn test_big_changes_color() {
+        let piece = Piece::big(Color::Green);
+        assert_eq!(piece.shape(), Shape::Big);
+        let turned = piece.rotated(3);
+        assert_eq!(turned.poyos()[0].color(), Color::Yellow);
+        assert_eq!(turned.rotated(3).poyos()[3].color(), Color::Red);
+        assert_eq!(turned.left(), piece.left());
+        let quad = Piece::quad(Color::Red, Color::Blue).rotated(4);
+        assert_eq!(quad.poyos()[0].color(), Color::Red);
+        assert_eq!(quad.poyos()[1].color(), Color::Red);
+        assert!(quad.pair().is_none());
+    }
+}
@@ -0,0 +1,534 @@
+use std::cmp::min;
+
+use action::Action;
+use ai::Ai;
+use bot::Bot;
+use chain::Chain;
+use direction::Direction;
+use fever::{self, Fever};
+use field::Field;
+use practice::{self, Command, Practice, Snapshot};
+use replay::Script;
+use stats::Stats;
+
+/// Frames between two gravity steps.
+const GRAVITY: u32 = 100;
+/// Fewest frames between two gravity steps, however high the level.
+const MIN_GRAVITY: u32 = 10;
+/// Pairs to lock before the level goes up.
+const LEVEL_PIECES: usize = 30;
+/// Score needed to send one garbage poyo.
+const TARGET_POINT: usize = 70;
+/// Points added to the chain after an all-clear, five rows of garbage.
+const ALL_CLEAR_BONUS: usize = 30 * TARGET_POINT;
+/// Frames the all-clear banner stays up.
+const BANNER: u32 = 200;
+/// Most garbage that falls at once (five rows).
+const MAX_GARBAGE: usize = 30;
+
+/// How fast pairs fall as the level goes up. By default they do not fall
+/// any faster; a `step` set in the settings speeds them up.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Curve {
+    /// Frames between two gravity steps at level 1.
+    pub start: u32,
+    /// Frames taken off at each level.
+    pub step: u32,
+    /// Fewest frames between two gravity steps.
+    pub min: u32,
+    /// Pairs to lock before the level goes up.
+    pub pieces: usize,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Curve {
+            start: GRAVITY,
+            step: 0,
+            min: MIN_GRAVITY,
+            pieces: LEVEL_PIECES,
+        }
+    }
+}
+
+pub enum Controller {
+    Human,
+    Cpu(Ai),
+    Bot(Bot),
+    /// Plays back recorded actions.
+    Replay(Script),
+}
+
+pub struct Player {
+    field: Field,
+    controller: Controller,
+    score: usize,
+    chain: usize,
+    garbage: usize,
+    leftover: usize,
+    start_level: usize,
+    curve: Curve,
+    gravity: u32,
+    dead: bool,
+    stats: Stats,
+    /// Whether the field was cleared, so the next chain scores the bonus.
+    all_clear: bool,
+    /// Bonus points of the last chain, still to be sent as garbage.
+    bonus: usize,
+    /// Frames left to show the all-clear banner.
+    banner: u32,
+    /// The fever gauge and countdown, when playing by fever rules.
+    fever: Option<Fever>,
+    /// Placements to undo and redo, when practicing.
+    practice: Option<Practice>,
+}
+
+impl Player {
+    pub fn new(field: Field, controller: Controller) -> Self {
+        let mut field = field;
+        if field.current().is_none() {
+            field.spawn();
+        }
+        Player {
+            field,
+            controller,
+            score: 0,
+            chain: 0,
+            garbage: 0,
+            leftover: 0,
+            start_level: 1,
+            curve: Curve::default(),
+            gravity: 0,
+            dead: false,
+            stats: Stats::default(),
+            all_clear: false,
+            bonus: 0,
+            banner: 0,
+            fever: None,
+            practice: None,
+        }
+    }
+
+    pub fn field(&self) -> &Field {
+        &self.field
+    }
+
+    pub fn controller_mut(&mut self) -> &mut Controller {
+        &mut self.controller
+    }
+
+    pub fn controller(&self) -> &Controller {
+        &self.controller
+    }
+
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    /// Length of the last chain fired.
+    pub fn chain(&self) -> usize {
+        self.chain
+    }
+
+    /// Longest chain fired so far.
+    pub fn max_chain(&self) -> usize {
+        self.stats.max_chain
+    }
+
+    /// Garbage waiting to fall on this field.
+    pub fn garbage(&self) -> usize {
+        self.garbage
+    }
+
+    /// Goes up every `Curve::pieces` pairs, making the pairs fall faster
+    /// if the curve has a step.
+    pub fn level(&self) -> usize {
+        self.start_level + self.stats.pieces / self.curve.pieces.max(1)
+    }
+
+    /// Level the game started at.
+    pub fn start_level(&self) -> usize {
+        self.start_level
+    }
+
+    pub fn set_start_level(&mut self, level: usize) {
+        self.start_level = level.max(1);
+    }
+
+    pub fn curve(&self) -> Curve {
+        self.curve
+    }
+
+    pub fn set_curve(&mut self, curve: Curve) {
+        self.curve = curve;
+    }
+
+    fn gravity(&self) -> u32 {
+        let faster = (self.level() as u32 - 1) * self.curve.step;
+        self.curve.start.saturating_sub(faster).max(self.curve.min)
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn fever(&self) -> Option<&Fever> {
+        self.fever.as_ref()
+    }
+
+    /// Plays by fever rules from now on.
+    pub fn set_fever(&mut self) {
+        self.fever = Some(Fever::default());
+        self.field.set_shapes(fever::SHAPES.to_vec());
+    }
+
+    pub fn practice(&self) -> Option<&Practice> {
+        self.practice.as_ref()
+    }
+
+    /// Practices from now on: no gravity, and pieces only lock on a hard
+    /// drop.
+    pub fn set_practice(&mut self) {
+        self.practice = Some(Practice::default());
+    }
+
+    /// Applies a practice command, returning whether anything changed.
+    pub fn on_command(&mut self, command: Command) -> bool {
+        if self.practice.is_none() {
+            return false;
+        }
+        match command {
+            Command::Undo | Command::Redo => {
+                let now = self.snapshot();
+                let practice = match self.practice.as_mut() {
+                    Some(practice) => practice,
+                    None => return false,
+                };
+                let to = if command == Command::Undo {
+                    practice.undo(now)
+                } else {
+                    practice.redo(now)
+                };
+                match to {
+                    Some(snapshot) => self.restore(snapshot),
+                    None => return false,
+                }
+            }
+            Command::Reroll => self.field.reroll_next(),
+            Command::Choose => {
+                let pair = practice::following(self.field.next().front(), self.field.colors());
+                self.field.set_next(pair);
+            }
+        }
+        true
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            field: self.field.clone(),
+            score: self.score,
+            chain: self.chain,
+            all_clear: self.all_clear,
+            stats: self.stats.clone(),
+            dead: self.dead,
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.field = snapshot.field;
+        self.score = snapshot.score;
+        self.chain = snapshot.chain;
+        self.all_clear = snapshot.all_clear;
+        self.stats = snapshot.stats;
+        self.dead = snapshot.dead;
+        self.banner = 0;
+        self.gravity = 0;
+    }
+
+    /// Text to show over the field for a while, such as after an
+    /// all-clear.
+    pub fn banner(&self) -> Option<&'static str> {
+        if self.banner > 0 {
+            Some("All Clear!")
+        } else {
+            None
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Applies an input. Pushing down on a pair that cannot fall any more
+    /// fixes it, in which case the resulting chain is returned.
+    pub fn on_action(&mut self, action: Action) -> Option<Chain> {
+        if self.dead {
+            return None;
+        }
+        match action {
+            Action::Left => self.field.move_current(Direction::Left),
+            Action::Right => self.field.move_current(Direction::Right),
+            Action::Rotate => self.field.rotate_current(),
+            Action::Down if self.practice.is_some() => {
+                let before = self.snapshot();
+                while self.field.current_can_move(&Direction::Down) {
+                    self.field.move_current(Direction::Down);
+                }
+                let chain = self.lock();
+                if let (Some(_), Some(practice)) = (&chain, self.practice.as_mut()) {
+                    practice.push(before);
+                }
+                return chain;
+            }
+            Action::Down => {
+                if !self.field.current_can_move(&Direction::Down) {
+                    return self.lock();
+                }
+                self.field.move_current(Direction::Down);
+                self.gravity = 0;
+            }
+        }
+        None
+    }
+
+    /// What the CPU, bot or replay wants to do this frame, called until it
+    /// returns `None`. Humans act through `on_action` directly.
+    pub fn plan(&mut self) -> Option<Action> {
+        if self.dead {
+            return None;
+        }
+        match self.controller {
+            Controller::Human => None,
+            Controller::Cpu(ref mut ai) => ai.action(&self.field),
+            Controller::Bot(ref mut bot) => bot.action(&self.field, self.garbage, self.score),
+            Controller::Replay(ref mut script) => script.action(),
+        }
+    }
+
+    /// Advances one frame of gravity.
+    pub fn on_frame(&mut self) -> Option<Chain> {
+        if let Controller::Replay(ref mut script) = self.controller {
+            script.tick();
+        }
+        if self.dead {
+            return None;
+        }
+        self.banner = self.banner.saturating_sub(1);
+        if let Some(ref mut fever) = self.fever {
+            fever.on_frame();
+        }
+        if self.practice.is_some() {
+            return None;
+        }
+        self.gravity += 1;
+        if self.gravity < self.gravity() {
+            return None;
+        }
+        self.gravity = 0;
+        if self.field.current_can_move(&Direction::Down) {
+            self.field.move_current(Direction::Down);
+            None
+        } else {
+            self.lock()
+        }
+    }
+
+    fn lock(&mut self) -> Option<Chain> {
+        let (chain, _) = self.field.lock_current()?;
+        self.bonus = 0;
+        if !chain.is_empty() && self.all_clear {
+            self.all_clear = false;
+            self.bonus = ALL_CLEAR_BONUS;
+        }
+        if chain.is_all_clear() {
+            self.all_clear = true;
+            self.banner = BANNER;
+        }
+        self.score += chain.score() + self.bonus;
+        self.stats.on_lock(&chain);
+        if let Some(ref mut fever) = self.fever {
+            fever.on_lock(&mut self.field, &chain);
+        }
+        let fever = self.fever.as_ref().is_some_and(|f| f.is_active());
+        if !chain.is_empty() {
+            self.chain = chain.len();
+        } else if self.garbage > 0 && !fever {
+            let count = min(self.garbage, MAX_GARBAGE);
+            self.field.drop_garbage(count);
+            self.garbage -= count;
+        }
+        if !self.field.spawn() {
+            self.dead = true;
+        }
+        match self.controller {
+            Controller::Cpu(ref mut ai) => ai.reset(),
+            Controller::Bot(ref mut bot) => bot.reset(),
+            _ => {}
+        }
+        self.gravity = 0;
+        Some(chain)
+    }
+
+    /// Turns a chain into garbage, with the all-clear bonus it scored and
+    /// the sun poyos it cleared, offsetting what is pending on this field
+    /// first. Offsetting fills the fever gauge. Returns how much is left to
+    /// send to the opponent.
+    pub fn attack(&mut self, chain: &Chain) -> usize {
+        let total = chain.score() + self.bonus + self.leftover;
+        self.bonus = 0;
+        self.leftover = total % TARGET_POINT;
+        let count = total / TARGET_POINT + chain.sun_garbage();
+        let offset = min(count, self.garbage);
+        self.garbage -= offset;
+        if let Some(ref mut fever) = self.fever {
+            if offset > 0 {
+                fever.on_offset(&mut self.field);
+            }
+        }
+        self.stats.garbage_sent += count - offset;
+        count - offset
+    }
+
+    pub fn receive(&mut self, garbage: usize) {
+        self.garbage += garbage;
+        self.stats.garbage_received += garbage;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Controller, Curve, Player};
+    use action::Action;
+    use chain::{Chain, ChainStep};
+    use color::Color;
+    use fever::GAUGE;
+    use field::Field;
+    use poyopoyo::PoyoPoyo;
+    use practice::Command;
+
+    fn chain(len: usize) -> Chain {
+        let mut chain = Chain::default();
+        for _ in 0..len {
+            let mut step = ChainStep::default();
+            step.push(Color::Red, 4);
+            chain.push(step);
+        }
+        chain
+    }
+
+    #[test]
+    fn test_attack_offsets_pending_garbage() {
+        let mut player = Player::new(Field::default(), Controller::Human);
+        player.receive(3);
+        // 40 + 320 = 360 points: five garbage and 10 points left over.
+        assert_eq!(player.attack(&chain(2)), 2);
+        assert_eq!(player.garbage(), 0);
+        assert_eq!(player.attack(&chain(1)), 0);
+    }
+
+    #[test]
+    fn test_offsets_fill_the_fever_gauge() {
+        let mut player = Player::new(Field::default(), Controller::Human);
+        player.set_fever();
+        for _ in 0..GAUGE {
+            assert!(!player.fever().unwrap().is_active());
+            player.receive(1);
+            player.attack(&chain(2));
+        }
+        assert!(player.fever().unwrap().is_active());
+        // Garbage waits for the fever to end.
+        player.receive(6);
+        while player.on_action(Action::Down).is_none() {}
+        assert_eq!(player.garbage(), 6);
+    }
+
+    #[test]
+    fn test_garbage_falls_after_lock() {
+        let mut player = Player::new(Field::default(), Controller::Human);
+        player.receive(6);
+        while player.on_action(Action::Down).is_none() {}
+        assert_eq!(player.garbage(), 0);
+        for x in 0..6 {
+            assert!(player.field().column_height(x) >= 1);
+        }
+    }
+
+    #[test]
+    fn test_all_clear_bonus() {
+        let mut field = Field::default();
+        let pairs = [Color::Red, Color::Red, Color::Blue, Color::Blue];
+        field.set_pairs(pairs.iter().map(|&c| PoyoPoyo::spawned(c, c)).collect());
+        let mut player = Player::new(field, Controller::Human);
+        let lock = |player: &mut Player| loop {
+            if let Some(chain) = player.on_action(Action::Down) {
+                return chain;
+            }
+        };
+        lock(&mut player);
+        let chain = lock(&mut player);
+        assert!(chain.is_all_clear());
+        assert_eq!(player.banner(), Some("All Clear!"));
+        assert_eq!(player.score(), 40);
+        lock(&mut player);
+        let chain = lock(&mut player);
+        assert!(chain.is_all_clear());
+        assert_eq!(player.score(), 40 + 40 + 2100);
+        // The bonus goes out as garbage with the chain that scored it, and
+        // only once: 40 points plus the 40 left over make one more.
+        assert_eq!(player.attack(&chain), 30);
+        assert_eq!(player.attack(&chain), 1);
+        assert_eq!(player.stats().all_clears, 2);
+    }
+
+    #[test]
+    fn test_level_speeds_up_gravity() {
+        let mut player = Player::new(Field::default(), Controller::Human);
+        assert_eq!((player.level(), player.gravity()), (1, 100));
+        player.stats.pieces = 95;
+        assert_eq!((player.level(), player.gravity()), (4, 100));
+        player.set_curve(Curve {
+            step: 10,
+            ..Curve::default()
+        });
+        assert_eq!((player.level(), player.gravity()), (4, 70));
+        player.stats.pieces = 1000;
+        assert_eq!(player.gravity(), 10);
+        let mut player = Player::new(Field::default(), Controller::Human);
+        player.set_start_level(3);
+        assert_eq!((player.level(), player.gravity()), (3, 100));
+        player.set_curve(Curve {
+            start: 60,
+            step: 20,
+            min: 5,
+            pieces: 10,
+        });
+        player.stats.pieces = 10;
+        assert_eq!((player.level(), player.gravity()), (4, 5));
+    }
+
+    #[test]
+    fn test_practice_undo_redo() {
+        let mut player = Player::new(Field::default(), Controller::Human);
+        player.set_practice();
+        for _ in 0..500 {
+            assert!(player.on_frame().is_none());
+        }
+        let start = player.field().current().cloned();
+        assert!(player.on_action(Action::Down).is_some());
+        let placed = player.field().rows().clone();
+        assert_eq!(player.stats().pieces, 1);
+        assert!(player.on_command(Command::Undo));
+        assert_eq!(player.field().current().cloned(), start);
+        assert_eq!(player.stats().pieces, 0);
+        assert!(!player.on_command(Command::Undo));
+        assert!(player.on_command(Command::Redo));
+        assert_eq!(player.field().rows(), &placed);
+        assert_eq!(player.practice().unwrap().undos(), 1);
+        assert!(!player.on_command(Command::Redo));
+        let next = player.field().next().front().cloned();
+        assert!(player.on_command(Command::Choose));
+        assert_ne!(player.field().next().front().cloned(), next);
+        let mut live = Player::new(Field::default(), Controller::Human);
+        assert!(!live.on_command(Command::Undo));
+    }
+}
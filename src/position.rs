@@ -38,6 +38,7 @@ impl Position {
     }
 }
 
+#[allow(clippy::derivable_impls)]
 impl Default for Position {
     fn default() -> Self {
         Position { x: 0, y: 0 }
@@ -0,0 +1,95 @@
+use color::Color;
+use field::Field;
+use position::Position;
+use poyo::Poyo;
+
+/// The biggest chain a field could fire with one more drop.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Potential {
+    pub chain: usize,
+    pub score: usize,
+    /// Where the first trigger poyo lands.
+    pub trigger: Position,
+    pub color: Color,
+    /// How many poyos of `color` the trigger takes: one or two.
+    pub count: usize,
+}
+
+impl Potential {
+    pub fn is_better_than(&self, other: &Potential) -> bool {
+        (self.chain, self.score, other.count) > (other.chain, other.score, self.count)
+    }
+}
+
+impl Field {
+    /// Tries dropping one, then two, poyos of every color into every column
+    /// and returns the longest chain found, the highest score breaking ties.
+    /// The current pair is left out. `None` if nothing can be fired.
+    pub fn potential(&self) -> Option<Potential> {
+        let board = self.simulate();
+        let mut best: Option<Potential> = None;
+        for x in 0..board.width() as i32 {
+            let trigger = Position::new(x, board.bottom() - 1 - board.column_height(x) as i32);
+            for &color in Color::all().iter() {
+                let mut sim = board.clone();
+                for count in 1..3 {
+                    if !sim.drop_poyo(Poyo::new(Position::new(x, 0), color)) {
+                        break;
+                    }
+                    if !sim.groups().iter().any(|g| g.1.len() >= 4) {
+                        continue;
+                    }
+                    let chain = sim.clone().resolve();
+                    let found = Potential {
+                        chain: chain.len(),
+                        score: chain.score(),
+                        trigger,
+                        color,
+                        count,
+                    };
+                    if best.map(|b| found.is_better_than(&b)).unwrap_or(true) {
+                        best = Some(found);
+                    }
+                    break;
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use color::Color::*;
+    use field::Field;
+    use position::Position;
+
+    #[test]
+    fn test_potential() {
+        // A red trigger on column 1 pops the reds and lets the greens on
+        // top of them fall onto the greens below.
+        let field =
+            Field::from_lines(&["G.....", "G.....", "R.....", "R.....", "RB....", "GG...."])
+                .unwrap();
+        let potential = field.potential().unwrap();
+        assert_eq!(potential.chain, 2);
+        assert_eq!(potential.color, Red);
+        assert_eq!(potential.count, 1);
+        assert_eq!(potential.trigger, Position::new(1, 3));
+        assert_eq!(potential.score, 40 + 320);
+    }
+
+    #[test]
+    fn test_potential_needs_two() {
+        let field = Field::from_lines(&["......", "......", "Y.....", "Y....."]).unwrap();
+        let potential = field.potential().unwrap();
+        assert_eq!(potential.chain, 1);
+        assert_eq!(potential.count, 2);
+        assert_eq!(potential.trigger, Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_no_potential() {
+        assert_eq!(Field::default().potential(), None);
+    }
+}
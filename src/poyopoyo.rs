@@ -6,6 +6,7 @@ use direction::Direction;
 use direction::Direction::*;
 use position::Position;
 use poyo::Poyo;
+use rand::Rng;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PoyoPoyo(pub Poyo, pub Poyo);
@@ -22,6 +23,22 @@ impl PoyoPoyo {
         )
     }
 
+    /// A pair at the spawn position of the first `colors` colors, drawn
+    /// from `rng` so that a seeded generator deals the same sequence every
+    /// time.
+    pub fn rand_from<R: Rng>(rng: &mut R, colors: u8) -> Self {
+        let pivot = Color::from(rng.gen::<u8>() % colors);
+        PoyoPoyo::spawned(pivot, Color::from(rng.gen::<u8>() % colors))
+    }
+
+    /// A pair of the given colors at the spawn position.
+    pub fn spawned(pivot: Color, child: Color) -> Self {
+        PoyoPoyo(
+            Poyo::new(Position::new(1, 0), pivot),
+            Poyo::new(Position::new(1, 1), child),
+        )
+    }
+
     pub fn x(&self) -> (i32, i32) {
         (self.0.x(), self.1.x())
     }
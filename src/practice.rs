@@ -0,0 +1,129 @@
+//! Practice: no gravity, pieces lock on a hard drop, and every placement
+//! can be undone and redone, so chain forms can be tried over and over.
+
+use color::Color;
+use field::Field;
+use piece::Piece;
+use poyopoyo::PoyoPoyo;
+use stats::Stats;
+
+/// Undoes the last placement.
+pub const UNDO: i32 = 0x7a;
+/// Redoes the placement last undone.
+pub const REDO: i32 = 0x79;
+/// Deals another next pair.
+pub const REROLL: i32 = 0x72;
+/// Steps the next pair through every pair of colors.
+pub const CHOOSE: i32 = 0x6e;
+/// Every command key with its name, which no player may bind.
+pub const COMMANDS: [(&str, i32); 4] = [
+    ("undo", UNDO),
+    ("redo", REDO),
+    ("reroll", REROLL),
+    ("choose", CHOOSE),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Undo,
+    Redo,
+    Reroll,
+    Choose,
+}
+
+impl Command {
+    pub fn from_key(key: i32) -> Option<Command> {
+        match key {
+            UNDO => Some(Command::Undo),
+            REDO => Some(Command::Redo),
+            REROLL => Some(Command::Reroll),
+            CHOOSE => Some(Command::Choose),
+            _ => None,
+        }
+    }
+}
+
+/// A player as they were before a placement: the field with its queue,
+/// and everything the placement scored.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub field: Field,
+    pub score: usize,
+    pub chain: usize,
+    pub all_clear: bool,
+    pub stats: Stats,
+    pub dead: bool,
+}
+
+/// Placements to go back to, and those gone back from.
+#[derive(Clone, Default)]
+pub struct Practice {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl Practice {
+    /// Notes a placement made from `before`; what was undone cannot be
+    /// redone any more.
+    pub fn push(&mut self, before: Snapshot) {
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    /// The snapshot to go back to from `now`, if any.
+    pub fn undo(&mut self, now: Snapshot) -> Option<Snapshot> {
+        let before = self.undo.pop()?;
+        self.redo.push(now);
+        Some(before)
+    }
+
+    /// The snapshot undone last, going forward from `now`.
+    pub fn redo(&mut self, now: Snapshot) -> Option<Snapshot> {
+        let after = self.redo.pop()?;
+        self.undo.push(now);
+        Some(after)
+    }
+
+    /// Placements that can be undone.
+    pub fn undos(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Placements that can be redone.
+    pub fn redos(&self) -> usize {
+        self.redo.len()
+    }
+}
+
+/// The pair after `piece` when stepping through every pair of the first
+/// `colors` colors, pivot first.
+pub fn following(piece: Option<&Piece>, colors: usize) -> PoyoPoyo {
+    let all = Color::all();
+    let colors = colors.clamp(1, all.len());
+    let index = |c: Color| all.iter().position(|&a| a == c).unwrap_or(0);
+    let n = match piece.and_then(|p| p.pair()) {
+        Some(pair) => {
+            let (pivot, child) = (index(pair.0.color()), index(pair.1.color()));
+            (pivot * colors + child + 1) % (colors * colors)
+        }
+        None => 0,
+    };
+    PoyoPoyo::spawned(all[n / colors], all[n % colors])
+}
+
+#[cfg(test)]
+mod test {
+    use super::following;
+    use piece::Piece;
+
+    #[test]
+    fn test_following_steps_through_every_pair() {
+        let first = Piece::from(following(None, 3));
+        let mut piece = first.clone();
+        for _ in 1..9 {
+            piece = Piece::from(following(Some(&piece), 3));
+            assert_ne!(piece, first);
+        }
+        assert_eq!(Piece::from(following(Some(&piece), 3)), first);
+    }
+}
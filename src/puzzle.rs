@@ -0,0 +1,238 @@
+//! Puzzles: a starting board, the exact pairs to place and an objective.
+//! Puzzle files are JSON, with the board in the field text form:
+//!
+//! ```json
+//! { "name": "First steps", "field": ["G.....", "R.....", "RGGG.."],
+//!   "pairs": ["RR"], "goal": { "chain": 2 } }
+//! ```
+//!
+//! Boards shorter than the field are padded with empty rows on top.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use color::Color;
+use field::Field;
+use paths;
+use player::Player;
+use poyopoyo::PoyoPoyo;
+use serde_json;
+
+/// Height of a puzzle's field, whatever the height of its board.
+const HEIGHT: usize = 12;
+
+/// What a player has to achieve.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Objective {
+    /// Fire a chain at least this long.
+    Chain(usize),
+    /// Score at least this many points.
+    Score(usize),
+    /// Send at least this much garbage.
+    Garbage(usize),
+    /// Pop groups of at least this many colors at once.
+    Colors(usize),
+    /// Stay alive until the time runs out; only a game with a clock can
+    /// tell when it is met.
+    Survive,
+}
+
+impl FromStr for Objective {
+    type Err = String;
+    /// `5-chain`, `30 garbage`, `3 colors`, `survive`, or a score as
+    /// `10000` or `10000 points`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let number = |n: &str| n.trim().parse().map_err(|_| format!("unknown goal: {}", s));
+        if s == "survive" {
+            Ok(Objective::Survive)
+        } else if let Some(n) = s.strip_suffix("-chain") {
+            number(n).map(Objective::Chain)
+        } else if let Some(n) = s.strip_suffix("garbage") {
+            number(n).map(Objective::Garbage)
+        } else if let Some(n) = s.strip_suffix("colors") {
+            number(n).map(Objective::Colors)
+        } else {
+            number(s.trim_end_matches("points")).map(Objective::Score)
+        }
+    }
+}
+
+impl Objective {
+    pub fn is_met(&self, player: &Player) -> bool {
+        *self != Objective::Survive && self.progress(player) >= self.amount()
+    }
+
+    /// How far `player` got, in the objective's unit.
+    pub fn progress(&self, player: &Player) -> usize {
+        match *self {
+            Objective::Chain(_) => player.max_chain(),
+            Objective::Score(_) => player.score(),
+            Objective::Garbage(_) => player.stats().garbage_sent,
+            Objective::Colors(_) => player.stats().max_colors,
+            Objective::Survive => 0,
+        }
+    }
+
+    /// The chain length, score, garbage or colors to reach.
+    pub fn amount(&self) -> usize {
+        match *self {
+            Objective::Chain(n)
+            | Objective::Score(n)
+            | Objective::Garbage(n)
+            | Objective::Colors(n) => n,
+            Objective::Survive => 0,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match *self {
+            Objective::Chain(n) => format!("fire a {}-chain", n),
+            Objective::Score(n) => format!("score {} points", n),
+            Objective::Garbage(n) => format!("send {} garbage", n),
+            Objective::Colors(n) => format!("pop {} colors at once", n),
+            Objective::Survive => "survive".to_string(),
+        }
+    }
+
+    /// A few words, to fit beside a field.
+    pub fn short(&self) -> String {
+        match *self {
+            Objective::Chain(n) => format!("{}-chain", n),
+            Objective::Score(n) => format!("{} points", n),
+            Objective::Garbage(n) => format!("{} garbage", n),
+            Objective::Colors(n) => format!("{} colors", n),
+            Objective::Survive => "survive".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub name: String,
+    pub field: Vec<String>,
+    /// Pivot then child, as color letters.
+    pub pairs: Vec<String>,
+    pub goal: Objective,
+}
+
+impl Puzzle {
+    pub fn builtin() -> Vec<Puzzle> {
+        serde_json::from_str(include_str!("../puzzles/builtin.json"))
+            .expect("the built-in puzzles are valid")
+    }
+
+    pub fn load(path: &Path) -> Result<Puzzle, String> {
+        let puzzle: Puzzle = paths::read_json(path)?;
+        puzzle.board()?;
+        Ok(puzzle)
+    }
+
+    /// The puzzles in `dir` by file name, leaving out those that do not
+    /// load.
+    pub fn saved(dir: &Path) -> Vec<Puzzle> {
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+            Err(_) => return vec![],
+        };
+        paths.sort();
+        paths
+            .iter()
+            .filter(|p| p.extension().is_some_and(|e| e == "json"))
+            .filter_map(|p| Puzzle::load(p).ok())
+            .collect()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        paths::save_json(path, self)
+    }
+
+    /// The starting board dealing the puzzle's pairs, before any spawn.
+    pub fn board(&self) -> Result<Field, String> {
+        let mut field = board(&self.field)?;
+        field.set_pairs(self.pairs()?);
+        Ok(field)
+    }
+
+    pub fn pairs(&self) -> Result<Vec<PoyoPoyo>, String> {
+        pairs(&self.pairs)
+    }
+}
+
+/// A field of `HEIGHT` rows with `rows` at the bottom, in the field text
+/// form.
+pub fn board(rows: &[String]) -> Result<Field, String> {
+    let width = rows.first().map(|l| l.chars().count()).unwrap_or(0);
+    let empty = ".".repeat(width);
+    let mut lines: Vec<&str> = vec![];
+    for _ in rows.len()..HEIGHT {
+        lines.push(&empty);
+    }
+    lines.extend(rows.iter().map(|l| l.as_str()));
+    Field::from_lines(&lines)
+}
+
+/// Pairs given as pivot then child color letters.
+pub fn pairs(pairs: &[String]) -> Result<Vec<PoyoPoyo>, String> {
+    pairs
+        .iter()
+        .map(|p| {
+            let colors: Vec<Option<Color>> = p.chars().map(Color::from_char).collect();
+            match colors.as_slice() {
+                [Some(pivot), Some(child)] if pivot.pops() && child.pops() => {
+                    Ok(PoyoPoyo::spawned(*pivot, *child))
+                }
+                _ => Err(format!("not a pair of colors: {}", p)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Objective, Puzzle};
+    use ai::Placement;
+    use field::Field;
+    use poyopoyo::PoyoPoyo;
+
+    /// Whether some sequence of placements of `pairs` fires `goal` chains.
+    fn solvable(field: &Field, pairs: &[PoyoPoyo], goal: usize) -> bool {
+        let (pair, rest) = match pairs.split_first() {
+            Some(split) => split,
+            None => return false,
+        };
+        Placement::all(field.width()).iter().any(|placement| {
+            let mut sim = field.simulate();
+            match sim.drop_pair(&placement.apply(pair)) {
+                Some(chain) if chain.len() >= goal => true,
+                Some(ref chain) if chain.is_empty() => solvable(&sim, rest, goal),
+                _ => false,
+            }
+        })
+    }
+
+    #[test]
+    fn test_builtin_puzzles_are_solvable() {
+        for puzzle in Puzzle::builtin() {
+            let goal = match puzzle.goal {
+                Objective::Chain(n) => n,
+                _ => continue,
+            };
+            let field = puzzle.board().unwrap();
+            assert_eq!(field.height(), 12);
+            let pairs = puzzle.pairs().unwrap();
+            assert!(solvable(&field, &pairs, goal), "{}", puzzle.name);
+            assert!(!solvable(&field, &pairs, goal + 1), "{}", puzzle.name);
+        }
+    }
+
+    #[test]
+    fn test_bad_pairs() {
+        let mut puzzle = Puzzle::builtin().remove(0);
+        puzzle.pairs = vec!["RO".to_string()];
+        assert!(puzzle.board().is_err());
+        puzzle.pairs = vec!["RGB".to_string()];
+        assert!(puzzle.pairs().is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Games against the clock: a time attack scores as much as it can before
+//! time runs out, a sprint meets its goal as fast as it can. Time is
+//! counted in frames, so it is exact and a replay ends on the same frame.
+
+use player::Player;
+use puzzle::Objective;
+
+/// Length of a time attack: two minutes.
+pub const ATTACK: u64 = 12_000;
+/// What a sprint is run for when no goal is given.
+pub const SPRINT_GOAL: Objective = Objective::Score(10_000);
+/// Frames counted down before the clock starts.
+pub const COUNTDOWN: u32 = 300;
+/// Splits taken over a game: every quarter of the time or of the goal.
+const SPLITS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Race {
+    /// Score as much as possible in this many frames.
+    Attack(u64),
+    /// Meet the objective as fast as possible.
+    Sprint(Objective),
+}
+
+/// Where the first player stood when a split was taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Split {
+    pub frame: u64,
+    pub score: usize,
+}
+
+impl Race {
+    /// Name of the mode and goal, as ranked in the high scores.
+    pub fn name(&self) -> String {
+        match *self {
+            Race::Attack(_) => "time-attack".to_string(),
+            Race::Sprint(goal) => format!("sprint {}", goal.short()),
+        }
+    }
+
+    /// The objective ending a sprint.
+    pub fn objective(&self) -> Option<Objective> {
+        match *self {
+            Race::Attack(_) => None,
+            Race::Sprint(goal) => Some(goal),
+        }
+    }
+
+    /// Whether the time of an attack is up at `frame`.
+    pub fn is_up(&self, frame: u64) -> bool {
+        match *self {
+            Race::Attack(limit) => frame >= limit,
+            Race::Sprint(_) => false,
+        }
+    }
+
+    /// Frames shown on the clock at `frame`: the time left in an attack,
+    /// the time taken in a sprint.
+    pub fn clock(&self, frame: u64) -> u64 {
+        match *self {
+            Race::Attack(limit) => limit.saturating_sub(frame),
+            Race::Sprint(_) => frame,
+        }
+    }
+
+    /// Whether split `n` (0-origin) is reached at `frame`.
+    pub fn is_split(&self, n: usize, frame: u64, player: &Player) -> bool {
+        if n >= SPLITS {
+            return false;
+        }
+        match *self {
+            Race::Attack(limit) => frame >= limit * (n as u64 + 1) / SPLITS as u64,
+            Race::Sprint(goal) => goal.progress(player) >= self.mark(goal, n),
+        }
+    }
+
+    /// Progress to reach for split `n` of a sprint to `goal`, rounded up.
+    fn mark(&self, goal: Objective, n: usize) -> usize {
+        (goal.amount() * (n + 1) + SPLITS - 1) / SPLITS
+    }
+
+    /// Labels and values of `splits`: the score at each quarter of an
+    /// attack, the time at each quarter of a sprint.
+    pub fn split_lines(&self, splits: &[Split]) -> Vec<(String, String)> {
+        splits
+            .iter()
+            .enumerate()
+            .map(|(n, split)| match *self {
+                Race::Attack(_) => (time(split.frame), split.score.to_string()),
+                Race::Sprint(goal) => {
+                    let mark = self.mark(goal, n);
+                    let label = match goal {
+                        Objective::Chain(_) => format!("{}-chain", mark),
+                        _ => mark.to_string(),
+                    };
+                    (label, time(split.frame))
+                }
+            })
+            .collect()
+    }
+}
+
+/// `m:ss.cc` of `frames`.
+pub fn time(frames: u64) -> String {
+    let secs = frames / 100;
+    format!("{}:{:02}.{:02}", secs / 60, secs % 60, frames % 100)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{time, Race, ATTACK};
+    use field::Field;
+    use game::Game;
+    use player::{Controller, Player};
+    use puzzle::Objective;
+
+    fn game(race: Race) -> Game {
+        let player = Player::new(Field::default(), Controller::Human);
+        Game::new(vec![player]).with_race(race)
+    }
+
+    #[test]
+    fn test_attack_runs_out() {
+        let mut game = game(Race::Attack(2000));
+        while !game.is_over() {
+            game.on_frame();
+        }
+        assert_eq!(game.frame(), 2000);
+        let frames: Vec<u64> = game.splits().iter().map(|s| s.frame).collect();
+        assert_eq!(frames, vec![500, 1000, 1500, 2000]);
+        let lines = game.race().unwrap().split_lines(game.splits());
+        assert_eq!(lines[1], ("0:10.00".to_string(), "0".to_string()));
+        assert_eq!(Race::Attack(ATTACK).clock(2950), 9050);
+        assert_eq!(time(9050), "1:30.50");
+    }
+
+    #[test]
+    fn test_sprint_splits() {
+        let goal: Objective = "6-chain".parse().unwrap();
+        let race = Race::Sprint(goal);
+        assert_eq!(race.name(), "sprint 6-chain");
+        let lines: Vec<String> = race
+            .split_lines(&[Default::default(); 4])
+            .into_iter()
+            .map(|l| l.0)
+            .collect();
+        assert_eq!(lines, vec!["2-chain", "3-chain", "5-chain", "6-chain"]);
+        assert_eq!("10000 points".parse(), Ok(Objective::Score(10_000)));
+        assert!("fast".parse::<Objective>().is_err());
+        let game = game(Race::Sprint(Objective::Score(100)));
+        assert_eq!(game.objective(), Some(Objective::Score(100)));
+        assert!(!game.is_over());
+    }
+}
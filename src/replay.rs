@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::path::Path;
+
+use action::Action;
+use color::Color;
+use field::Field;
+use game::{Game, Record};
+use paths;
+use player::{Controller, Curve, Player};
+use race::Race;
+use size::Size;
+
+/// Everything needed to play a match again: both fields are dealt from
+/// `seed` and receive the recorded actions on the same frames.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u32,
+    pub width: usize,
+    pub height: usize,
+    pub players: Vec<String>,
+    pub records: Vec<Record>,
+    pub frames: u64,
+    pub winner: Option<usize>,
+    /// Level every player started at.
+    #[serde(default = "first_level")]
+    pub level: usize,
+    /// Number of colors the pairs were dealt from.
+    #[serde(default = "all_colors")]
+    pub colors: usize,
+    #[serde(default)]
+    pub curve: Curve,
+    /// Whether the game was played by fever rules.
+    #[serde(default)]
+    pub fever: bool,
+    /// The clock the game was played against, if any.
+    #[serde(default)]
+    pub race: Option<Race>,
+}
+
+fn first_level() -> usize {
+    1
+}
+
+fn all_colors() -> usize {
+    Color::all().len()
+}
+
+impl Replay {
+    pub fn new(seed: u32, size: &Size, players: Vec<String>, game: &Game) -> Self {
+        let first = game.players().first();
+        Replay {
+            seed,
+            width: size.width,
+            height: size.height,
+            players,
+            records: game.records().to_vec(),
+            frames: game.frame(),
+            winner: game.winner(),
+            level: first.map(|p| p.start_level()).unwrap_or(1),
+            colors: first.map(|p| p.field().colors()).unwrap_or_else(all_colors),
+            curve: first.map(|p| p.curve()).unwrap_or_default(),
+            fever: first.is_some_and(|p| p.fever().is_some()),
+            race: game.race(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        paths::read_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        paths::save_json(path, self)
+    }
+
+    /// A game at its first frame, with every seat playing back its records.
+    pub fn game(&self) -> Game {
+        let players = (0..self.players.len())
+            .map(|i| {
+                let size = Size::new(self.width, self.height);
+                let field = Field::with_colors(size, self.seed, self.colors);
+                let mut player =
+                    Player::new(field, Controller::Replay(Script::new(&self.records, i)));
+                player.set_start_level(self.level);
+                player.set_curve(self.curve);
+                player
+            })
+            .collect();
+        let game = Game::new(players);
+        let game = if self.fever { game.with_fever() } else { game };
+        match self.race {
+            Some(race) => game.with_race(race),
+            None => game,
+        }
+    }
+
+    /// Plays the whole replay back without a view.
+    pub fn run(&self) -> Game {
+        let mut game = self.game();
+        while game.frame() < self.frames && !game.is_over() {
+            game.on_frame();
+        }
+        game
+    }
+}
+
+/// The recorded actions of one seat, handed out frame by frame.
+pub struct Script {
+    frame: u64,
+    actions: VecDeque<(u64, Action)>,
+}
+
+impl Script {
+    pub fn new(records: &[Record], player: usize) -> Self {
+        Script {
+            frame: 0,
+            actions: records
+                .iter()
+                .filter(|r| r.player == player)
+                .map(|r| (r.frame, r.action))
+                .collect(),
+        }
+    }
+
+    pub fn action(&mut self) -> Option<Action> {
+        match self.actions.front() {
+            Some(&(frame, _)) if frame <= self.frame => self.actions.pop_front().map(|a| a.1),
+            _ => None,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.frame += 1;
+    }
+}
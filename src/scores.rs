@@ -0,0 +1,203 @@
+//! Local high scores, one table per mode and rule set, kept as JSON in
+//! `$XDG_DATA_HOME/poyo/scores.json`.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use paths;
+
+/// Entries kept in each table.
+pub const KEEP: usize = 10;
+/// Longest name that can be entered.
+pub const NAME_LENGTH: usize = 12;
+/// Tables of sprints rank the fastest first.
+const SPRINT: &str = "sprint";
+
+/// What a score is comparable under: games with other rules go in
+/// another table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    pub mode: String,
+    pub width: usize,
+    pub height: usize,
+    pub colors: usize,
+    pub level: usize,
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}x{} {} colors level {}",
+            self.mode, self.width, self.height, self.colors, self.level
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub score: usize,
+    pub max_chain: usize,
+    /// Length of the game in frames.
+    pub frames: u64,
+    /// When the game ended, in seconds since the Unix epoch.
+    pub date: u64,
+    pub seed: u32,
+}
+
+impl Entry {
+    /// `m:ss` of play.
+    pub fn time(&self) -> String {
+        let secs = self.frames / 100;
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+
+    /// `yyyy-mm-dd`, in UTC.
+    pub fn day(&self) -> String {
+        // Howard Hinnant's days-to-civil algorithm.
+        let z = (self.date / 86_400) as i64 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + (month <= 2) as i64;
+        format!("{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+/// Every table, by the text of its rules.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Scores {
+    tables: BTreeMap<String, Vec<Entry>>,
+}
+
+impl Scores {
+    pub fn path() -> PathBuf {
+        paths::data_dir().join("scores.json")
+    }
+
+    /// The scores in `path`, or none if there is no such file yet.
+    pub fn load(path: &Path) -> Result<Scores, String> {
+        paths::load_json(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        paths::save_json(path, self)
+    }
+
+    /// Names of the tables with at least one entry.
+    pub fn tables(&self) -> Vec<String> {
+        self.tables.keys().cloned().collect()
+    }
+
+    pub fn table(&self, rules: &str) -> &[Entry] {
+        self.tables.get(rules).map(|t| t.as_slice()).unwrap_or(&[])
+    }
+
+    /// Where `entry` would rank, if it makes the table at all: by score,
+    /// or by time in a sprint. Ties go below the entries already there.
+    pub fn rank(&self, rules: &str, entry: &Entry) -> Option<usize> {
+        let by_time = rules.starts_with(SPRINT);
+        let table = self.table(rules);
+        let rank = table
+            .iter()
+            .take_while(|e| {
+                if by_time {
+                    e.frames <= entry.frames
+                } else {
+                    e.score >= entry.score
+                }
+            })
+            .count();
+        let counts = if by_time {
+            entry.frames > 0
+        } else {
+            entry.score > 0
+        };
+        if rank < KEEP && counts {
+            Some(rank)
+        } else {
+            None
+        }
+    }
+
+    /// Adds `entry` where it ranks, dropping whatever falls off the end.
+    pub fn insert(&mut self, rules: &str, entry: Entry) -> Option<usize> {
+        let rank = self.rank(rules, &entry)?;
+        let table = self.tables.entry(rules.to_string()).or_default();
+        table.insert(rank, entry);
+        table.truncate(KEEP);
+        Some(rank)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Entry, Rules, Scores, KEEP};
+
+    fn entry(score: usize) -> Entry {
+        Entry {
+            name: "p".to_string(),
+            score,
+            max_chain: 2,
+            frames: 12_345,
+            date: 1_700_000_000,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn test_ranking() {
+        let rules = Rules {
+            mode: "endless".to_string(),
+            width: 6,
+            height: 12,
+            colors: 4,
+            level: 1,
+        }
+        .to_string();
+        assert_eq!(rules, "endless 6x12 4 colors level 1");
+        let mut scores = Scores::default();
+        assert_eq!(scores.rank(&rules, &entry(0)), None);
+        for score in 1..=KEEP {
+            scores.insert(&rules, entry(score * 100));
+        }
+        assert_eq!(scores.table(&rules)[0].score, 1000);
+        assert_eq!(scores.rank(&rules, &entry(50)), None);
+        assert_eq!(scores.rank(&rules, &entry(1000)), Some(1));
+        assert_eq!(scores.insert(&rules, entry(550)), Some(5));
+        assert_eq!(scores.table(&rules).len(), KEEP);
+        assert_eq!(scores.table(&rules)[KEEP - 1].score, 200);
+        assert_eq!(scores.tables(), vec![rules]);
+        assert!(scores.table("versus").is_empty());
+    }
+
+    #[test]
+    fn test_sprints_rank_by_time() {
+        let rules = "sprint 5-chain 6x12 4 colors level 1";
+        let mut scores = Scores::default();
+        for &frames in &[3000, 1000, 2000] {
+            let entry = Entry { frames, ..entry(1) };
+            scores.insert(rules, entry);
+        }
+        let frames: Vec<u64> = scores.table(rules).iter().map(|e| e.frames).collect();
+        assert_eq!(frames, vec![1000, 2000, 3000]);
+        let unfinished = Entry {
+            frames: 0,
+            ..entry(1)
+        };
+        assert_eq!(scores.rank(rules, &unfinished), None);
+    }
+
+    #[test]
+    fn test_entry_text() {
+        let entry = entry(1);
+        assert_eq!(entry.time(), "2:03");
+        assert_eq!(entry.day(), "2023-11-14");
+    }
+}
@@ -0,0 +1,275 @@
+use editor::Editor;
+use game_state::GameState;
+use menu::Menu;
+use mission::{Campaign, Mission};
+use puzzle::Puzzle;
+use scores::{Entry, Scores, KEEP};
+use settings::Settings;
+use theme::BUILTIN;
+
+/// The menus, from the title down.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Page {
+    Title,
+    Endless,
+    Versus,
+    Fever,
+    TimeAttack,
+    Sprint,
+    Puzzle,
+    Mission,
+    Replay,
+    Settings,
+}
+
+/// What is on the terminal and what the keys drive.
+pub enum Screen {
+    Menu(Page, Menu),
+    Game(GameState),
+    /// The stats of the game just over.
+    Summary,
+    Scores(Board),
+    Editor(Box<Editor>),
+}
+
+/// A high score table, with a new record in it while its name is typed.
+pub struct Board {
+    /// Tables that can be browsed with left and right, and the one shown.
+    pub tables: Vec<String>,
+    pub table: usize,
+    pub entries: Vec<Entry>,
+    /// Row of the new record.
+    pub highlight: Option<usize>,
+    /// Name of the new record as it is typed.
+    pub typing: Option<String>,
+    pub message: Option<String>,
+}
+
+pub const OFF_ON: [&str; 2] = ["off", "on"];
+pub const LEVELS: [&str; 3] = ["easy", "normal", "hard"];
+pub const OPPONENTS: [&str; 2] = ["cpu", "human"];
+pub const COLORS: [&str; 2] = ["3", "4"];
+pub const MODES: [&str; 8] = [
+    "menu",
+    "endless",
+    "versus",
+    "fever",
+    "time-attack",
+    "sprint",
+    "practice",
+    "watch-ai",
+];
+/// Goals a sprint can be run for.
+pub const GOALS: [&str; 6] = [
+    "5000 points",
+    "10000 points",
+    "30000 points",
+    "3-chain",
+    "5-chain",
+    "7-chain",
+];
+
+pub fn title() -> Screen {
+    let menu = Menu::new("poyo")
+        .entry("endless")
+        .entry("versus")
+        .entry("fever")
+        .entry("time attack")
+        .entry("sprint")
+        .entry("puzzle")
+        .entry("mission")
+        .entry("practice")
+        .entry("editor")
+        .entry("replay")
+        .entry("scores")
+        .entry("settings")
+        .entry("quit");
+    Screen::Menu(Page::Title, menu)
+}
+
+pub fn endless() -> Screen {
+    let levels: Vec<String> = (1..10).map(|l| l.to_string()).collect();
+    let menu = Menu::new("endless")
+        .option("level", &levels, "1")
+        .entry("start");
+    Screen::Menu(Page::Endless, menu)
+}
+
+/// A time attack or a sprint, from a starting level.
+pub fn race(page: Page) -> Screen {
+    let levels: Vec<String> = (1..10).map(|l| l.to_string()).collect();
+    let mut menu = if page == Page::Sprint {
+        Menu::new("sprint").option("level", &levels, "1").option(
+            "goal",
+            &to_strings(&GOALS),
+            "10000 points",
+        )
+    } else {
+        Menu::new("time attack").option("level", &levels, "1")
+    };
+    menu = menu.entry("start");
+    Screen::Menu(page, menu)
+}
+
+/// Versus or fever, against the CPU or a second human.
+pub fn versus(page: Page, settings: &Settings) -> Screen {
+    let level = format!("{:?}", settings.level).to_lowercase();
+    let title = if page == Page::Fever {
+        "fever"
+    } else {
+        "versus"
+    };
+    let mut menu = Menu::new(title)
+        .option("opponent", &OPPONENTS, "cpu")
+        .option("cpu level", &LEVELS, &level)
+        .entry("start");
+    menu.message = Some("second human: a d s w".to_string());
+    Screen::Menu(page, menu)
+}
+
+pub fn puzzles(puzzles: &[Puzzle]) -> Screen {
+    let mut menu = Menu::new("puzzle");
+    for (i, puzzle) in puzzles.iter().enumerate() {
+        let label = format!("{}. {} ({})", i + 1, puzzle.name, puzzle.goal.describe());
+        menu = menu.entry(&label);
+    }
+    Screen::Menu(Page::Puzzle, menu)
+}
+
+/// The missions in order, those not yet open without their goal.
+pub fn missions(missions: &[Mission], campaign: &Campaign) -> Screen {
+    let mut menu = Menu::new("mission");
+    for (i, mission) in missions.iter().enumerate() {
+        let label = if campaign.is_open(i) {
+            format!("{}. {} ({})", i + 1, mission.name, mission.describe())
+        } else {
+            format!("{}. locked", i + 1)
+        };
+        menu = menu.entry(&label);
+    }
+    menu.message = Some(format!("{}/{} cleared", campaign.cleared, missions.len()));
+    Screen::Menu(Page::Mission, menu)
+}
+
+/// Replay files by name, newest first.
+pub fn replays(names: &[String]) -> Screen {
+    let mut menu = Menu::new("replay");
+    for name in names {
+        menu = menu.entry(name);
+    }
+    if names.is_empty() {
+        menu.message = Some("no replays yet".to_string());
+    }
+    Screen::Menu(Page::Replay, menu)
+}
+
+/// Settings shown in the Settings menu, by label.
+pub const SETTINGS: [(&str, &str, &str); 16] = [
+    ("theme", "view", "theme"),
+    ("colorblind", "view", "colorblind"),
+    ("joined", "view", "joined"),
+    ("colors", "game", "colors"),
+    ("cpu level", "cpu", "level"),
+    ("start in", "game", "mode"),
+    ("das", "input", "das"),
+    ("arr", "input", "arr"),
+    ("p1 left", "keys", "left"),
+    ("p1 right", "keys", "right"),
+    ("p1 down", "keys", "down"),
+    ("p1 rotate", "keys", "rotate"),
+    ("p2 left", "second keys", "left"),
+    ("p2 right", "second keys", "right"),
+    ("p2 down", "second keys", "down"),
+    ("p2 rotate", "second keys", "rotate"),
+];
+
+/// Section and name of the setting labelled `label`.
+pub fn setting(label: &str) -> Option<(&'static str, &'static str)> {
+    SETTINGS
+        .iter()
+        .find(|&&(l, _, _)| l == label)
+        .map(|&(_, section, key)| (section, key))
+}
+
+/// Whether a setting is changed by pressing the key to bind.
+pub fn is_key(section: &str) -> bool {
+    section.ends_with("keys")
+}
+
+/// The settings, with the cursor on entry `cursor`.
+pub fn settings(settings: &Settings, cursor: usize) -> Screen {
+    let mut menu = Menu::new("settings");
+    for &(label, section, key) in SETTINGS.iter() {
+        let value = settings.get(section, key).unwrap_or_default();
+        let values: Vec<String> = match key {
+            "theme" => {
+                let mut themes: Vec<String> =
+                    BUILTIN.iter().map(|&(name, _)| name.to_string()).collect();
+                if !themes.contains(&value) {
+                    themes.push(value.clone());
+                }
+                themes
+            }
+            "colorblind" | "joined" => to_strings(&OFF_ON),
+            "colors" => to_strings(&COLORS),
+            "level" => to_strings(&LEVELS),
+            "mode" => to_strings(&MODES),
+            "das" | "arr" => (0..31).map(|n| n.to_string()).collect(),
+            _ => vec![value.clone()],
+        };
+        menu = menu.option(label, &values, &value);
+    }
+    menu = menu.entry("back");
+    menu.set_cursor(cursor);
+    Screen::Menu(Page::Settings, menu)
+}
+
+fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+/// Table `table` of every table kept, to browse from the title menu.
+pub fn scores(scores: &Scores, table: usize) -> Screen {
+    let tables = scores.tables();
+    let table = table.min(tables.len().saturating_sub(1));
+    let entries = tables
+        .get(table)
+        .map(|t| scores.table(t).to_vec())
+        .unwrap_or_default();
+    let message = if tables.is_empty() {
+        "no scores yet"
+    } else {
+        "left and right for other rules"
+    };
+    Screen::Scores(Board {
+        tables,
+        table,
+        entries,
+        highlight: None,
+        typing: None,
+        message: Some(message.to_string()),
+    })
+}
+
+/// The table of `rules` after a game, with `record` in it if it ranks.
+pub fn result(scores: &Scores, rules: &str, record: Option<&Entry>) -> Screen {
+    let mut entries = scores.table(rules).to_vec();
+    let highlight = record.and_then(|e| scores.rank(rules, e));
+    if let (Some(rank), Some(entry)) = (highlight, record) {
+        entries.insert(rank, entry.clone());
+        entries.truncate(KEEP);
+    }
+    let message = if highlight.is_some() {
+        "new record! type your name, enter to save"
+    } else {
+        "enter to go on"
+    };
+    Screen::Scores(Board {
+        tables: vec![rules.to_string()],
+        table: 0,
+        entries,
+        highlight,
+        typing: highlight.and(record).map(|e| e.name.clone()),
+        message: Some(message.to_string()),
+    })
+}
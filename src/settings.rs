@@ -0,0 +1,407 @@
+//! Settings kept between runs, in `$XDG_CONFIG_HOME/poyo/settings.ini`:
+//!
+//! ```ini
+//! [game]
+//! mode = menu
+//! width = 6
+//! [keys]
+//! rotate = space
+//! ```
+//!
+//! Missing settings keep their default and command line flags win over
+//! the file. Times are in frames of 10 ms.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use action::{key_name, parse_key, Action, Keys};
+use ai::Level;
+use color::Color;
+use options::{Mode, Options};
+use paths;
+use player::Curve;
+use practice::COMMANDS;
+use size::Size;
+use theme::Theme;
+
+/// Narrowest and widest field, in columns.
+pub const WIDTHS: (usize, usize) = (3, 16);
+/// Shortest and tallest field, in rows.
+pub const HEIGHTS: (usize, usize) = (4, 24);
+
+/// Every setting, by section and name, in the order they are written.
+pub const FIELDS: [(&str, &str); 22] = [
+    ("game", "mode"),
+    ("game", "width"),
+    ("game", "height"),
+    ("game", "colors"),
+    ("gravity", "start"),
+    ("gravity", "step"),
+    ("gravity", "min"),
+    ("gravity", "pieces"),
+    ("input", "das"),
+    ("input", "arr"),
+    ("keys", "left"),
+    ("keys", "right"),
+    ("keys", "down"),
+    ("keys", "rotate"),
+    ("second keys", "left"),
+    ("second keys", "right"),
+    ("second keys", "down"),
+    ("second keys", "rotate"),
+    ("view", "theme"),
+    ("view", "colorblind"),
+    ("view", "joined"),
+    ("cpu", "level"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// What starts without a mode on the command line.
+    pub mode: Mode,
+    pub width: usize,
+    pub height: usize,
+    /// Number of colors pairs are dealt from.
+    pub colors: usize,
+    pub curve: Curve,
+    /// Frames a move key is held before it repeats.
+    pub das: u32,
+    /// Frames between two repeated moves.
+    pub arr: u32,
+    /// Keys of the first and second human.
+    pub keys: [Keys; 2],
+    /// Name of a built-in theme or path of a theme file.
+    pub theme: String,
+    pub colorblind: bool,
+    pub joined: bool,
+    /// Level of the CPU opponent.
+    pub level: Level,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: Mode::Menu,
+            width: 6,
+            height: 12,
+            colors: Color::all().len(),
+            curve: Curve::default(),
+            das: 15,
+            arr: 3,
+            keys: [Keys::first(), Keys::second()],
+            theme: "classic".to_string(),
+            colorblind: false,
+            joined: false,
+            level: Level::Normal,
+        }
+    }
+}
+
+impl Settings {
+    /// Where the settings are kept.
+    pub fn path() -> PathBuf {
+        paths::config_dir().join("settings.ini")
+    }
+
+    /// The settings in `path`, or the defaults if there is no such file.
+    pub fn load(path: &Path) -> Result<Settings, String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Settings::parse(&text).map_err(|e| format!("{}: {}", path.display(), e)),
+            Err(ref e) if e.kind() == ::std::io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Writes every setting to `path`. Comments of an edited file are lost.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        }
+        fs::write(path, self.to_ini())
+            .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+    }
+
+    pub fn parse(text: &str) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        let mut section = String::new();
+        for (i, line) in text.lines().enumerate() {
+            let at = |e: String| format!("line {}: {}", i + 1, e);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].trim().to_string();
+                if !FIELDS.iter().any(|&(s, _)| s == section) {
+                    return Err(at(format!("unknown section [{}]", section)));
+                }
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(eq) => (line[..eq].trim(), line[eq + 1..].trim()),
+                None => return Err(at(format!("expected name = value, got {}", line))),
+            };
+            settings.set(&section, key, value).map_err(at)?;
+        }
+        settings.check()?;
+        Ok(settings)
+    }
+
+    pub fn to_ini(&self) -> String {
+        let mut ini = "# poyo settings. Times are in frames of 10 ms.\n".to_string();
+        let mut section = "";
+        for &(s, key) in FIELDS.iter() {
+            if s != section {
+                section = s;
+                ini += &format!("\n[{}]\n", section);
+            }
+            ini += &format!("{} = {}\n", key, self.get(section, key).unwrap_or_default());
+        }
+        ini
+    }
+
+    /// A setting as it is written in the file.
+    pub fn get(&self, section: &str, key: &str) -> Option<String> {
+        let on_off = |b: bool| if b { "on" } else { "off" }.to_string();
+        let value = match (section, key) {
+            ("game", "mode") => self.mode.name().to_string(),
+            ("game", "width") => self.width.to_string(),
+            ("game", "height") => self.height.to_string(),
+            ("game", "colors") => self.colors.to_string(),
+            ("gravity", "start") => self.curve.start.to_string(),
+            ("gravity", "step") => self.curve.step.to_string(),
+            ("gravity", "min") => self.curve.min.to_string(),
+            ("gravity", "pieces") => self.curve.pieces.to_string(),
+            ("input", "das") => self.das.to_string(),
+            ("input", "arr") => self.arr.to_string(),
+            ("keys", action) | ("second keys", action) => {
+                let keys = &self.keys[(section == "second keys") as usize];
+                let action = Action::all().iter().cloned().find(|a| a.name() == action)?;
+                key_name(keys.key(action)).unwrap_or_default()
+            }
+            ("view", "theme") => self.theme.clone(),
+            ("view", "colorblind") => on_off(self.colorblind),
+            ("view", "joined") => on_off(self.joined),
+            ("cpu", "level") => format!("{:?}", self.level).to_lowercase(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    /// Changes one setting, checking its value on its own.
+    pub fn set(&mut self, section: &str, key: &str, value: &str) -> Result<(), String> {
+        let name = format!("{}.{}", section, key);
+        match (section, key) {
+            ("game", "mode") => self.mode = value.parse()?,
+            ("game", "width") => self.width = number(&name, value, WIDTHS.0, WIDTHS.1)?,
+            ("game", "height") => self.height = number(&name, value, HEIGHTS.0, HEIGHTS.1)?,
+            ("game", "colors") => self.colors = number(&name, value, 3, Color::all().len())?,
+            ("gravity", "start") => self.curve.start = number(&name, value, 1, 1000)?,
+            ("gravity", "step") => self.curve.step = number(&name, value, 0, 1000)?,
+            ("gravity", "min") => self.curve.min = number(&name, value, 1, 1000)?,
+            ("gravity", "pieces") => self.curve.pieces = number(&name, value, 1, 1000)?,
+            ("input", "das") => self.das = number(&name, value, 0, 100)?,
+            ("input", "arr") => self.arr = number(&name, value, 0, 100)?,
+            ("keys", action) | ("second keys", action) => {
+                let player = (section == "second keys") as usize;
+                let action = Action::all()
+                    .iter()
+                    .cloned()
+                    .find(|a| a.name() == action)
+                    .ok_or_else(|| format!("unknown setting {}", name))?;
+                let key = parse_key(value).map_err(|e| format!("{}: {}", name, e))?;
+                self.keys[player].bind(action, key);
+            }
+            ("view", "theme") => self.theme = value.to_string(),
+            ("view", "colorblind") => self.colorblind = switch(&name, value)?,
+            ("view", "joined") => self.joined = switch(&name, value)?,
+            ("cpu", "level") => self.level = value.parse()?,
+            _ => return Err(format!("unknown setting {}", name)),
+        }
+        Ok(())
+    }
+
+    /// Checks the settings that depend on each other.
+    pub fn check(&self) -> Result<(), String> {
+        if self.curve.min > self.curve.start {
+            return Err(format!(
+                "gravity.min ({}) is slower than gravity.start ({})",
+                self.curve.min, self.curve.start
+            ));
+        }
+        for (player, keys) in ["keys", "second keys"].iter().zip(self.keys.iter()) {
+            let actions = Action::all();
+            for (i, &a) in actions.iter().enumerate() {
+                if let Some(&b) = actions[i + 1..]
+                    .iter()
+                    .find(|&&b| keys.key(a) == keys.key(b))
+                {
+                    return Err(format!(
+                        "{}: {} and {} share a key",
+                        player,
+                        a.name(),
+                        b.name()
+                    ));
+                }
+                if let Some(&(command, _)) = COMMANDS.iter().find(|c| c.1 == keys.key(a)) {
+                    return Err(format!(
+                        "{}: {} takes the key of the practice command {}",
+                        player,
+                        a.name(),
+                        command
+                    ));
+                }
+            }
+        }
+        let (first, second) = (&self.keys[0], &self.keys[1]);
+        for &a in Action::all().iter() {
+            if let Some(b) = second.action(first.key(a)) {
+                return Err(format!(
+                    "keys.{} and second keys.{} share a key",
+                    a.name(),
+                    b.name()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The settings with the command line's choices on top, checked like
+    /// those of the file.
+    pub fn with_options(self, options: &Options) -> Result<Self, String> {
+        let mut settings = Settings {
+            mode: options.mode,
+            theme: options.theme.clone(),
+            colorblind: options.colorblind,
+            joined: options.joined,
+            level: options.level,
+            ..self
+        };
+        let numbers = [
+            ("colors", options.colors),
+            ("width", options.width),
+            ("height", options.height),
+        ];
+        for &(key, value) in numbers.iter() {
+            if let Some(n) = value {
+                settings.set("game", key, &n.to_string())?;
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Defaults for the command line flags that override a setting.
+    pub fn options(&self) -> Options {
+        Options {
+            mode: self.mode,
+            theme: self.theme.clone(),
+            colorblind: self.colorblind,
+            joined: self.joined,
+            level: self.level,
+            ..Options::default()
+        }
+    }
+
+    pub fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+
+    /// The theme with the colorblind and joined rendering options applied.
+    pub fn theme(&self) -> Result<Theme, String> {
+        let mut theme = Theme::named(&self.theme)?;
+        if self.colorblind {
+            theme = theme.with_colorblind();
+        }
+        if !self.joined {
+            theme.joined = None;
+        }
+        Ok(theme)
+    }
+}
+
+fn number<T>(name: &str, value: &str, min: T, max: T) -> Result<T, String>
+where
+    T: ::std::str::FromStr + PartialOrd + ::std::fmt::Display,
+{
+    match value.parse() {
+        Ok(n) if n >= min && n <= max => Ok(n),
+        _ => Err(format!(
+            "{} must be a number from {} to {}, got {}",
+            name, min, max, value
+        )),
+    }
+}
+
+fn switch(name: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(format!("{} must be on or off, got {}", name, value)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Settings;
+    use ai::Level;
+    use options::{Mode, Options};
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = Settings::default();
+        settings.set("game", "colors", "3").unwrap();
+        settings.set("second keys", "rotate", "enter").unwrap();
+        settings.set("cpu", "level", "hard").unwrap();
+        assert_eq!(Settings::parse(&settings.to_ini()), Ok(settings));
+        let text = "# mine\n[game]\nmode = versus\n\n[view]\njoined = on\n";
+        let settings = Settings::parse(text).unwrap();
+        assert_eq!((settings.mode, settings.joined), (Mode::Versus, true));
+        assert_eq!(settings.level, Level::Normal);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| Settings::parse(text).unwrap_err();
+        assert_eq!(
+            error("[game]\nwidth = 20\n"),
+            "line 2: game.width must be a number from 3 to 16, got 20"
+        );
+        assert_eq!(error("[sound]\n"), "line 1: unknown section [sound]");
+        assert_eq!(
+            error("[game]\nspeed = 3\n"),
+            "line 2: unknown setting game.speed"
+        );
+        assert_eq!(error("width = 6\n"), "line 1: unknown setting .width");
+        assert_eq!(
+            error("[game]\nmode = bench\n"),
+            "line 2: unknown mode: bench"
+        );
+        assert_eq!(
+            error("[keys]\nleft = q\n"),
+            "line 2: keys.left: q is kept for quitting"
+        );
+        assert_eq!(
+            error("[keys]\nleft = down\n"),
+            "keys: left and down share a key"
+        );
+        assert_eq!(
+            error("[keys]\nleft = a\n"),
+            "keys.left and second keys.left share a key"
+        );
+        assert_eq!(
+            error("[second keys]\nrotate = z\n"),
+            "second keys: rotate takes the key of the practice command undo"
+        );
+        assert!(Settings::parse("[game]\nwidth = 10\nheight = 20\n").is_ok());
+        assert!(Settings::parse("[gravity]\nmin = 200\n").is_err());
+        assert!(Settings::parse("[view]\njoined = maybe\n").is_err());
+        let options = Options {
+            width: Some(2),
+            ..Options::default()
+        };
+        assert_eq!(
+            Settings::default().with_options(&options),
+            Err("game.width must be a number from 3 to 16, got 2".to_string())
+        );
+    }
+}
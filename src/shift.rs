@@ -0,0 +1,163 @@
+//! Delayed auto-shift for left and right. A held key moves once, waits
+//! `das` frames, then moves every `arr` frames, whatever the terminal's
+//! own key repeat rate.
+//!
+//! Terminals report key presses only, repeating them while a key is held.
+//! They wait a while before the first repeat, then repeat quickly. So a
+//! second press sooner than any terminal's first repeat is a new tap, one
+//! in time for it is taken for the first repeat and the key as held, and
+//! once held every press coming as fast as repeats do is one. The key is
+//! released when the repeats stop coming.
+
+use action::Action;
+
+/// Frames to wait for the terminal's first repeat before taking a press
+/// for a tap. Terminals start repeating after 250 to 600 ms.
+const FIRST_REPEAT: u32 = 60;
+/// Frames before which a second press cannot be the first repeat.
+const FIRST_REPEAT_MIN: u32 = 20;
+/// Frames without a repeat after which a held key is released. Terminals
+/// repeat every 30 to 50 ms.
+const RELEASE: u32 = 8;
+/// Moves made at once when `arr` is 0: more than any field is wide.
+const INSTANT: usize = 16;
+
+struct Held {
+    action: Action,
+    /// Frames since the key went down.
+    frames: u32,
+    /// Frames since the last press or repeat.
+    idle: u32,
+    /// Whether the terminal repeated the key, so it is being held.
+    repeating: bool,
+}
+
+pub struct AutoShift {
+    das: u32,
+    arr: u32,
+    held: Option<Held>,
+}
+
+impl AutoShift {
+    pub fn new(das: u32, arr: u32) -> Self {
+        AutoShift {
+            das,
+            arr,
+            held: None,
+        }
+    }
+
+    /// A press of `action`, or the terminal repeating it. Returns whether
+    /// the press moves at once; repeats are left to `on_frame`.
+    pub fn press(&mut self, action: Action) -> bool {
+        let first_repeat = match self.held {
+            Some(ref mut held) if held.action == action => {
+                if held.repeating && held.idle <= RELEASE {
+                    held.idle = 0;
+                    return false;
+                }
+                !held.repeating && held.idle >= FIRST_REPEAT_MIN
+            }
+            _ => false,
+        };
+        // The first repeat moves like a tap, and the shift is timed from it.
+        self.held = Some(Held {
+            action,
+            frames: 0,
+            idle: 0,
+            repeating: first_repeat,
+        });
+        true
+    }
+
+    pub fn release(&mut self) {
+        self.held = None;
+    }
+
+    /// Advances a frame. Returns the move due this frame, if any, and how
+    /// many times to make it.
+    pub fn on_frame(&mut self) -> Option<(Action, usize)> {
+        let (das, arr) = (self.das, self.arr);
+        let released = match self.held {
+            Some(ref mut held) => {
+                held.frames += 1;
+                held.idle += 1;
+                let timeout = if held.repeating {
+                    RELEASE
+                } else {
+                    FIRST_REPEAT
+                };
+                held.idle > timeout
+            }
+            None => return None,
+        };
+        if released {
+            self.release();
+            return None;
+        }
+        let held = self.held.as_ref()?;
+        if !held.repeating || held.frames < das {
+            return None;
+        }
+        match arr {
+            0 => Some((held.action, INSTANT)),
+            _ if (held.frames - das) % arr == 0 => Some((held.action, 1)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{AutoShift, FIRST_REPEAT, FIRST_REPEAT_MIN};
+    use action::Action;
+
+    /// Moves made over `frames` frames, with the key pressed or repeated
+    /// on the frames in `presses`.
+    fn moves(shift: &mut AutoShift, frames: u32, presses: &[u32]) -> usize {
+        (1..=frames)
+            .map(|frame| {
+                let now = presses.contains(&frame) && shift.press(Action::Left);
+                now as usize + shift.on_frame().map(|(_, n)| n).unwrap_or(0)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_tap_moves_once() {
+        let mut shift = AutoShift::new(10, 2);
+        assert!(shift.press(Action::Left));
+        assert_eq!(moves(&mut shift, FIRST_REPEAT + 5, &[]), 0);
+        assert!(shift.press(Action::Left));
+    }
+
+    #[test]
+    fn test_double_tap_moves_twice() {
+        let mut shift = AutoShift::new(15, 3);
+        assert!(shift.press(Action::Left));
+        assert_eq!(moves(&mut shift, FIRST_REPEAT + 10, &[12]), 1);
+        // As fast as terminals repeat, but before any of them starts.
+        let mut shift = AutoShift::new(15, 3);
+        assert!(shift.press(Action::Left));
+        assert_eq!(moves(&mut shift, FIRST_REPEAT + 10, &[5]), 1);
+    }
+
+    #[test]
+    fn test_hold_repeats_at_arr() {
+        let mut shift = AutoShift::new(10, 2);
+        assert!(shift.press(Action::Left));
+        // The terminal starts repeating after 30 frames, every 4 frames.
+        // Its first repeat moves like a tap, the next ones shift at arr.
+        assert_eq!(moves(&mut shift, 29, &[]), 0);
+        assert_eq!(moves(&mut shift, 20, &[1, 5, 9, 13, 17]), 1 + 6);
+        // Once the repeats stop the key is released.
+        assert_eq!(moves(&mut shift, 20, &[]), 2);
+        assert!(shift.press(Action::Left));
+        assert!(shift.press(Action::Right));
+        let mut instant = AutoShift::new(0, 0);
+        instant.press(Action::Right);
+        moves(&mut instant, FIRST_REPEAT_MIN, &[]);
+        instant.press(Action::Right);
+        assert_eq!(instant.on_frame(), Some((Action::Right, 16)));
+    }
+}
@@ -0,0 +1,148 @@
+//! What a player did over a game, shown beside the well and after the
+//! game, and kept as JSON in `$XDG_DATA_HOME/poyo/stats` for analysis.
+
+use std::path::Path;
+
+use chain::Chain;
+use paths;
+
+/// Frames in a minute of play.
+const MINUTE: u64 = 6000;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats {
+    /// Pairs locked.
+    pub pieces: usize,
+    /// Longest chain fired.
+    pub max_chain: usize,
+    /// How many chains of each length were fired, from 1-chains up.
+    pub chains: Vec<usize>,
+    /// Chains that left the field empty.
+    pub all_clears: usize,
+    /// Most poyos popped at once, in one step of a chain.
+    pub max_pop: usize,
+    /// Most colors popped at once.
+    #[serde(default)]
+    pub max_colors: usize,
+    pub garbage_sent: usize,
+    pub garbage_received: usize,
+}
+
+impl Stats {
+    /// Counts a pair locked, and the chain it fired.
+    pub fn on_lock(&mut self, chain: &Chain) {
+        self.pieces += 1;
+        if chain.is_empty() {
+            return;
+        }
+        let len = chain.len();
+        if self.chains.len() < len {
+            self.chains.resize(len, 0);
+        }
+        self.chains[len - 1] += 1;
+        self.max_chain = self.max_chain.max(len);
+        let pop = chain.steps().iter().map(|s| s.removed()).max();
+        self.max_pop = self.max_pop.max(pop.unwrap_or(0));
+        let colors = chain.steps().iter().map(|s| s.colors()).max();
+        self.max_colors = self.max_colors.max(colors.unwrap_or(0));
+        if chain.is_all_clear() {
+            self.all_clears += 1;
+        }
+    }
+
+    /// Pieces locked per minute over `frames` frames of play.
+    pub fn per_minute(&self, frames: u64) -> f64 {
+        if frames == 0 {
+            return 0.0;
+        }
+        self.pieces as f64 * MINUTE as f64 / frames as f64
+    }
+
+    /// Labels and values, the chain histogram last, from 1-chains up to
+    /// the longest chain fired.
+    pub fn lines(&self, frames: u64) -> Vec<(String, String)> {
+        let mut lines = vec![
+            ("pieces".to_string(), self.pieces.to_string()),
+            (
+                "per min".to_string(),
+                format!("{:.1}", self.per_minute(frames)),
+            ),
+            ("max chain".to_string(), self.max_chain.to_string()),
+            ("max pop".to_string(), self.max_pop.to_string()),
+            ("all clear".to_string(), self.all_clears.to_string()),
+            ("sent".to_string(), self.garbage_sent.to_string()),
+            ("received".to_string(), self.garbage_received.to_string()),
+        ];
+        for (i, count) in self.chains.iter().enumerate() {
+            lines.push((format!("{}-chain", i + 1), count.to_string()));
+        }
+        lines
+    }
+}
+
+/// One player's part of a `Report`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Seat {
+    pub name: String,
+    pub score: usize,
+    pub pieces_per_minute: f64,
+    pub stats: Stats,
+}
+
+/// The stats of every player of a game, as written out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub seed: u32,
+    pub frames: u64,
+    /// When the game ended, in seconds since the Unix epoch.
+    pub date: u64,
+    pub players: Vec<Seat>,
+}
+
+impl Report {
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        paths::save_json(path, self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Stats;
+    use chain::{Chain, ChainStep};
+    use color::Color;
+
+    fn chain(sizes: &[usize], all_clear: bool) -> Chain {
+        let mut chain = Chain::default();
+        for &size in sizes {
+            let mut step = ChainStep::default();
+            step.push(Color::Red, size);
+            chain.push(step);
+        }
+        if all_clear {
+            chain.set_all_clear();
+        }
+        chain
+    }
+
+    #[test]
+    fn test_on_lock() {
+        let mut stats = Stats::default();
+        stats.on_lock(&chain(&[], false));
+        stats.on_lock(&chain(&[4, 6, 4], false));
+        stats.on_lock(&chain(&[5], true));
+        assert_eq!(stats.pieces, 3);
+        assert_eq!(stats.chains, vec![1, 0, 1]);
+        assert_eq!(
+            (stats.max_chain, stats.max_pop, stats.all_clears),
+            (3, 6, 1)
+        );
+        assert_eq!(stats.max_colors, 1);
+        // Three pieces in half a minute.
+        assert_eq!(stats.per_minute(3000), 6.0);
+        assert_eq!(stats.per_minute(0), 0.0);
+        let lines = stats.lines(3000);
+        assert_eq!(lines[1], ("per min".to_string(), "6.0".to_string()));
+        assert_eq!(lines.last().unwrap().0, "3-chain");
+        assert_eq!(lines.len(), 10);
+    }
+}
@@ -0,0 +1,361 @@
+//! How poyos and borders look. A theme is a JSON file with one swatch
+//! per color and the borders of the wells and panels:
+//!
+//! ```json
+//! {
+//!   "red": { "glyph": "*", "fg": "#000000", "bg": "#e63c3c", "mono": ["bold"] },
+//!   ...
+//!   "well": { "vertical": "|", "horizontal": "-", "top_left": "+", ... },
+//!   "panel": { ... }
+//! }
+//! ```
+//!
+//! Colors are given as RGB and brought down to whatever the terminal can
+//! show. The built-in themes live in `themes/`.
+
+use std::convert::TryFrom;
+use std::fs;
+
+use color::Color;
+use direction::Direction;
+use field::Links;
+use serde_json;
+
+/// How many colors the terminal can show, from none at all to any RGB.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Depth {
+    /// No colors: poyos are told apart by glyph and attribute only.
+    Mono,
+    /// The eight ANSI colors.
+    Basic,
+    /// The xterm 256-color palette.
+    Extended,
+    /// 256 colors that can be redefined to exact RGB values.
+    TrueColor,
+}
+
+impl Depth {
+    pub fn detect(has_colors: bool, colors: i32, can_change: bool) -> Depth {
+        if !has_colors {
+            Depth::Mono
+        } else if colors < 256 {
+            Depth::Basic
+        } else if can_change {
+            Depth::TrueColor
+        } else {
+            Depth::Extended
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Attr {
+    Bold,
+    Underline,
+    Reverse,
+    Dim,
+}
+
+/// A color written `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let hex = s.trim_start_matches('#');
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+                .ok_or_else(|| format!("not a #rrggbb color: {}", s))
+        };
+        if hex.len() != 6 {
+            return Err(format!("not a #rrggbb color: {}", s));
+        }
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl Rgb {
+    /// The ANSI color with each channel on if it is at least half lit.
+    pub fn basic(&self) -> i16 {
+        let on = |c: u8, bit: i16| if c >= 128 { bit } else { 0 };
+        on(self.0, 1) | on(self.1, 2) | on(self.2, 4)
+    }
+
+    /// The nearest color of the xterm 6x6x6 cube.
+    pub fn extended(&self) -> i16 {
+        const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+        let level = |c: u8| {
+            (0..6)
+                .min_by_key(|&i| (LEVELS[i] as i32 - c as i32).abs())
+                .unwrap_or(0) as i16
+        };
+        16 + 36 * level(self.0) + 6 * level(self.1) + level(self.2)
+    }
+
+    /// The channels on the 0-1000 scale `init_color` takes.
+    pub fn scaled(&self) -> (i16, i16, i16) {
+        let scale = |c: u8| (c as i32 * 1000 / 255) as i16;
+        (scale(self.0), scale(self.1), scale(self.2))
+    }
+}
+
+/// How one color is drawn.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Swatch {
+    /// One column wide.
+    pub glyph: String,
+    /// Two columns wide, for wide cells. Defaults to the glyph twice.
+    #[serde(default)]
+    pub wide: Option<String>,
+    #[serde(default)]
+    pub fg: Option<Rgb>,
+    #[serde(default)]
+    pub bg: Option<Rgb>,
+    #[serde(default)]
+    pub attrs: Vec<Attr>,
+    /// Extra attributes standing in for the colors on monochrome terminals.
+    #[serde(default)]
+    pub mono: Vec<Attr>,
+}
+
+impl Swatch {
+    /// Text of a cell `cell_width` columns wide.
+    pub fn text(&self, cell_width: i32) -> String {
+        match self.wide {
+            Some(ref wide) if cell_width == 2 => wide.clone(),
+            _ => self.glyph.repeat(cell_width as usize),
+        }
+    }
+}
+
+/// Color number to draw `rgb` with at `depth`, -1 being the terminal's
+/// default. `slot` is the color redefined to `rgb` on true color terminals.
+pub fn color_number(rgb: Option<Rgb>, depth: Depth, slot: i16) -> i16 {
+    match (rgb, depth) {
+        (None, _) | (_, Depth::Mono) => -1,
+        (Some(rgb), Depth::Basic) => rgb.basic(),
+        (Some(rgb), Depth::Extended) => rgb.extended(),
+        (Some(_), Depth::TrueColor) => slot,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Border {
+    pub vertical: String,
+    pub horizontal: String,
+    pub top_left: String,
+    pub top_right: String,
+    pub bottom_left: String,
+    pub bottom_right: String,
+}
+
+/// Glyphs drawing groups as one piece, picked by which neighbours a cell
+/// is joined to.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Joined {
+    /// Sixteen glyphs indexed by `Links::index`.
+    pub glyphs: Vec<String>,
+    /// Second column of a wide cell joined to its right.
+    pub joint: String,
+}
+
+impl Joined {
+    pub fn text(&self, links: Links, cell_width: i32) -> String {
+        let mut text = self.glyphs[links.index()].clone();
+        if cell_width == 2 {
+            text += if links.has(Direction::Right) {
+                &self.joint
+            } else {
+                " "
+            };
+        }
+        text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Theme {
+    pub red: Swatch,
+    pub green: Swatch,
+    pub yellow: Swatch,
+    pub blue: Swatch,
+    pub garbage: Swatch,
+    /// The special poyos. Themes without them borrow those of the classic
+    /// theme.
+    #[serde(default = "default_hard")]
+    pub hard: Swatch,
+    #[serde(default = "default_point")]
+    pub point: Swatch,
+    #[serde(default = "default_sun")]
+    pub sun: Swatch,
+    #[serde(default = "default_block")]
+    pub block: Swatch,
+    pub well: Border,
+    pub panel: Border,
+    /// Glyph marking where the current pair will land, in its color. Empty
+    /// for the poyo's own glyph.
+    #[serde(default = "default_ghost")]
+    pub ghost: String,
+    /// Themes without it draw every poyo on its own.
+    #[serde(default)]
+    pub joined: Option<Joined>,
+}
+
+fn default_ghost() -> String {
+    ".".to_string()
+}
+
+fn default_hard() -> Swatch {
+    Theme::default().hard
+}
+
+fn default_point() -> Swatch {
+    Theme::default().point
+}
+
+fn default_sun() -> Swatch {
+    Theme::default().sun
+}
+
+fn default_block() -> Swatch {
+    Theme::default().block
+}
+
+/// Themes compiled in, by name.
+pub const BUILTIN: [(&str, &str); 5] = [
+    ("ascii", include_str!("../themes/ascii.json")),
+    ("blocks", include_str!("../themes/blocks.json")),
+    ("circles", include_str!("../themes/circles.json")),
+    ("classic", include_str!("../themes/classic.json")),
+    ("colorblind", include_str!("../themes/colorblind.json")),
+];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::builtin("classic").expect("the classic theme exists")
+    }
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Theme> {
+        BUILTIN.iter().find(|&&(n, _)| n == name).map(|&(n, json)| {
+            serde_json::from_str(json)
+                .unwrap_or_else(|e| panic!("the built-in theme {} is invalid: {}", n, e))
+        })
+    }
+
+    pub fn load(path: &str) -> Result<Theme, String> {
+        let json = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let theme: Theme = serde_json::from_str(&json).map_err(|e| format!("{}: {}", path, e))?;
+        match theme.joined {
+            Some(ref joined) if joined.glyphs.len() != 16 => {
+                Err(format!("{}: joined needs 16 glyphs", path))
+            }
+            _ => Ok(theme),
+        }
+    }
+
+    /// A built-in theme, or else the theme file at `name`.
+    pub fn named(name: &str) -> Result<Theme, String> {
+        match Theme::builtin(name) {
+            Some(theme) => Ok(theme),
+            None => Theme::load(name),
+        }
+    }
+
+    /// This theme with the poyos and ghost of the colorblind theme: a
+    /// distinct shape per color, in colors that stay apart with any kind of
+    /// color blindness. The borders are kept; joined glyphs are dropped, as
+    /// they look the same whatever the color.
+    pub fn with_colorblind(self) -> Theme {
+        let colorblind = Theme::builtin("colorblind").expect("the colorblind theme exists");
+        Theme {
+            well: self.well,
+            panel: self.panel,
+            joined: None,
+            ..colorblind
+        }
+    }
+
+    pub fn swatch(&self, color: Color) -> &Swatch {
+        match color {
+            Color::Red => &self.red,
+            Color::Green => &self.green,
+            Color::Yellow => &self.yellow,
+            Color::Blue => &self.blue,
+            Color::Garbage => &self.garbage,
+            Color::Hard => &self.hard,
+            Color::Point => &self.point,
+            Color::Sun => &self.sun,
+            Color::Block => &self.block,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Depth, Rgb, Theme, BUILTIN};
+    use color::Color;
+    use serde_json;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Depth::detect(false, 256, true), Depth::Mono);
+        assert_eq!(Depth::detect(true, 8, true), Depth::Basic);
+        assert_eq!(Depth::detect(true, 256, false), Depth::Extended);
+        assert_eq!(Depth::detect(true, 256, true), Depth::TrueColor);
+    }
+
+    #[test]
+    fn test_rgb() {
+        let blue = Rgb::try_from("#3c6ee6".to_string()).unwrap();
+        assert_eq!(blue, Rgb(0x3c, 0x6e, 0xe6));
+        assert_eq!(blue.basic(), 4);
+        assert_eq!(Rgb(255, 0, 0).extended(), 196);
+        assert_eq!(Rgb(255, 255, 255).scaled(), (1000, 1000, 1000));
+        assert!(Rgb::try_from("red".to_string()).is_err());
+        assert!(Rgb::try_from("#12345g".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_builtin_themes_are_distinct() {
+        let colors = Color::every();
+        for &(name, _) in BUILTIN.iter() {
+            let theme = Theme::builtin(name).unwrap();
+            for &color in &colors {
+                assert_ne!(theme.ghost, theme.swatch(color).glyph, "{}", name);
+            }
+            for (i, &a) in colors.iter().enumerate() {
+                for &b in &colors[i + 1..] {
+                    let (a, b) = (theme.swatch(a), theme.swatch(b));
+                    assert!(a.glyph != b.glyph || a.mono != b.mono, "{}", name);
+                    assert!(a.fg != b.fg || a.bg != b.bg, "{}", name);
+                }
+            }
+        }
+        assert_eq!(Theme::default().red.text(2), "**");
+        assert_eq!(Theme::builtin("circles").unwrap().red.text(2), "● ");
+        assert!(Theme::named("no/such/theme.json").is_err());
+        let theme: Theme =
+            serde_json::from_str(&BUILTIN[0].1.replace("\"sun\"", "\"moon\"")).unwrap();
+        assert_eq!(theme.sun, Theme::default().sun);
+        let theme = Theme::default().with_colorblind();
+        assert_eq!(theme.green.glyph, "▲");
+        assert!(Theme::builtin("blocks")
+            .unwrap()
+            .with_colorblind()
+            .joined
+            .is_none());
+        assert_eq!(theme.well, Theme::default().well);
+        assert!(Theme::default().joined.is_none());
+        for name in &["blocks", "circles"] {
+            let joined = Theme::builtin(name).unwrap().joined.unwrap();
+            assert_eq!(joined.glyphs.len(), 16);
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+
+use ai::{Ai, Level};
+use bot::Bot;
+use field::Field;
+use game::Game;
+use player::{Controller, Player};
+use replay::Replay;
+use size::Size;
+
+/// Frames after which a match is called a draw: ten minutes of play.
+pub const MAX_FRAMES: u64 = 100 * 60 * 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Entrant {
+    Cpu(Level),
+    Bot(String),
+}
+
+impl Entrant {
+    pub fn name(&self) -> String {
+        match *self {
+            Entrant::Cpu(level) => format!("cpu:{:?}", level).to_lowercase(),
+            Entrant::Bot(ref command) => command.clone(),
+        }
+    }
+
+    fn controller(&self) -> Result<Controller, String> {
+        match *self {
+            Entrant::Cpu(level) => Ok(Controller::Cpu(Ai::new(level))),
+            Entrant::Bot(ref command) => {
+                let mut bot = Bot::spawn(command)?;
+                bot.set_blocking(true);
+                Ok(Controller::Bot(bot))
+            }
+        }
+    }
+}
+
+/// How one match went, from the entrants' point of view.
+pub struct MatchResult {
+    pub seed: u32,
+    /// Index of the winning entrant, `None` for a draw.
+    pub winner: Option<usize>,
+    pub scores: [usize; 2],
+    pub max_chains: [usize; 2],
+    pub replay: Replay,
+}
+
+/// Versus matches between two entrants with no view. Match `n` is dealt
+/// from `seed + n / 2` and every other match swaps the seats, so both
+/// entrants play each sequence from both sides.
+pub struct Tournament {
+    entrants: [Entrant; 2],
+    seed: u32,
+    size: Size,
+}
+
+impl Tournament {
+    pub fn new(entrants: [Entrant; 2], seed: u32, size: Size) -> Self {
+        Tournament {
+            entrants,
+            seed,
+            size,
+        }
+    }
+
+    pub fn entrant(&self, i: usize) -> &Entrant {
+        &self.entrants[i]
+    }
+
+    pub fn play(&self, n: usize) -> Result<MatchResult, String> {
+        let seed = self.seed.wrapping_add(n as u32 / 2);
+        let seats = if n % 2 == 0 { [0, 1] } else { [1, 0] };
+        let mut players = vec![];
+        for &seat in &seats {
+            let field = Field::with_seed(self.size.clone(), seed);
+            players.push(Player::new(field, self.entrants[seat].controller()?));
+        }
+        let mut game = Game::new(players);
+        while !game.is_over() && game.frame() < MAX_FRAMES {
+            game.on_frame();
+            for player in game.players() {
+                if let Controller::Bot(ref bot) = *player.controller() {
+                    if let Some(e) = bot.error() {
+                        return Err(format!("{}: {}", bot.command(), e));
+                    }
+                }
+            }
+        }
+        let mut scores = [0; 2];
+        let mut max_chains = [0; 2];
+        for (player, &seat) in game.players().iter().zip(seats.iter()) {
+            scores[seat] = player.score();
+            max_chains[seat] = player.max_chain();
+        }
+        let names = seats.iter().map(|&s| self.entrants[s].name()).collect();
+        Ok(MatchResult {
+            seed,
+            winner: game.winner().map(|w| seats[w]),
+            scores,
+            max_chains,
+            replay: Replay::new(seed, &self.size, names, &game),
+        })
+    }
+
+    /// Plays `games` matches, handing each result to `on_match` as it ends.
+    pub fn run<F>(&self, games: usize, mut on_match: F) -> Result<Report, String>
+    where
+        F: FnMut(usize, &MatchResult) -> Result<(), String>,
+    {
+        let mut report = Report {
+            names: [self.entrants[0].name(), self.entrants[1].name()],
+            ..Default::default()
+        };
+        for n in 0..games {
+            let result = self.play(n)?;
+            report.add(&result);
+            on_match(n, &result)?;
+        }
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    names: [String; 2],
+    games: usize,
+    wins: [usize; 2],
+    chains: [usize; 2],
+    scores: [usize; 2],
+}
+
+impl Report {
+    fn add(&mut self, result: &MatchResult) {
+        self.games += 1;
+        if let Some(w) = result.winner {
+            self.wins[w] += 1;
+        }
+        for i in 0..2 {
+            self.chains[i] += result.max_chains[i];
+            self.scores[i] += result.scores[i];
+        }
+    }
+
+    pub fn draws(&self) -> usize {
+        self.games - self.wins[0] - self.wins[1]
+    }
+
+    pub fn win_rate(&self, entrant: usize) -> f64 {
+        self.average(self.wins[entrant])
+    }
+
+    /// Average of the longest chain of each match.
+    pub fn average_chain(&self, entrant: usize) -> f64 {
+        self.average(self.chains[entrant])
+    }
+
+    pub fn average_score(&self, entrant: usize) -> f64 {
+        self.average(self.scores[entrant])
+    }
+
+    fn average(&self, total: usize) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            total as f64 / self.games as f64
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} games, {} draws", self.games, self.draws())?;
+        for i in 0..2 {
+            writeln!(
+                f,
+                "{}: win rate {:.1}%, average chain {:.2}, average score {:.0}",
+                self.names[i],
+                self.win_rate(i) * 100.0,
+                self.average_chain(i),
+                self.average_score(i)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Entrant, Tournament};
+    use ai::Level;
+    use size::Size;
+
+    #[test]
+    fn test_replay_matches_result() {
+        let tournament = Tournament::new(
+            [Entrant::Cpu(Level::Normal), Entrant::Cpu(Level::Easy)],
+            7,
+            Size::new(6, 12),
+        );
+        let result = tournament.play(1).unwrap();
+        let game = result.replay.run();
+        assert_eq!(game.winner(), result.replay.winner);
+        // Seats are swapped on odd matches.
+        assert_eq!(game.players()[1].score(), result.scores[0]);
+        assert_eq!(game.players()[0].score(), result.scores[1]);
+    }
+
+    #[test]
+    fn test_report() {
+        let tournament = Tournament::new(
+            [Entrant::Cpu(Level::Easy), Entrant::Cpu(Level::Easy)],
+            3,
+            Size::new(6, 12),
+        );
+        let mut played = 0;
+        let report = tournament
+            .run(2, |_, _| {
+                played += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(played, 2);
+        // Identical entrants on mirrored seeds split the two matches.
+        assert_eq!(report.wins[0], report.wins[1]);
+    }
+}